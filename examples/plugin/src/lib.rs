@@ -7,6 +7,9 @@ scotch_guest::export_alloc!();
 extern "C" {
     fn print(val: &String);
     fn random_cat_fact() -> [String; 2];
+    fn cat_fact(index: u32) -> Result<String, String>;
+    fn uppercase(text: &mut String);
+    fn log_line(line: &str);
 }
 
 #[scotch_guest::guest_function]
@@ -32,3 +35,66 @@ fn greet(name: &String) -> String {
     let [fact1, fact2] = random_cat_fact();
     format!("Hello, {name}! Did you know that {fact1} and {fact2}")
 }
+
+#[scotch_guest::guest_function]
+fn parse_number(text: &String) -> Result<i32, String> {
+    text.parse::<i32>().map_err(|e| e.to_string())
+}
+
+#[scotch_guest::guest_function]
+fn describe_fact(index: u32) -> String {
+    match cat_fact(index) {
+        Ok(fact) => format!("Fact: {fact}"),
+        Err(e) => format!("Error: {e}"),
+    }
+}
+
+// Floats and pointer sized integers are passed natively.
+#[scotch_guest::guest_function]
+fn scale(value: f64, factor: f32, times: usize, shift: isize) -> f64 {
+    value * factor as f64 * times as f64 + shift as f64
+}
+
+#[scotch_guest::guest_function]
+fn count_items(items: &Vec<i32>) -> usize {
+    items.len()
+}
+
+// 128 bit integers are passed as two `u64`.
+#[scotch_guest::guest_function]
+fn wide_sum(a: u128, b: i128) -> i128 {
+    a as i128 + b
+}
+
+// Changes made to mutable references are written back to the host.
+#[scotch_guest::guest_function]
+fn extend_list(list: &mut Vec<i32>, count: u32) {
+    let last = list.last().copied().unwrap_or_default();
+    list.extend((1..=count as i32).map(|i| last + i));
+}
+
+#[scotch_guest::guest_function]
+fn shout(name: &String) -> String {
+    let mut text = format!("Hello, {name}!");
+    uppercase(&mut text);
+    text
+}
+
+// Byte slices and strings are copied as is, without encoding.
+#[scotch_guest::guest_function]
+fn checksum(data: &[u8]) -> u32 {
+    data.iter().map(|&byte| byte as u32).sum()
+}
+
+#[scotch_guest::guest_function]
+fn invert(data: &[u8]) -> Vec<u8> {
+    data.iter().map(|byte| !byte).collect()
+}
+
+#[scotch_guest::guest_function]
+fn reverse(text: &str) -> String {
+    #[cfg(not(bench))]
+    log_line(text);
+
+    text.chars().rev().collect()
+}
@@ -1,6 +1,9 @@
 use common::Object;
 use eyre::Result;
-use scotch_host::{guest_functions, host_function, make_exports, make_imports, WasmPlugin};
+use scotch_host::{
+    guest_functions, host_function, make_exports, make_imports, GuestError, GuestResultExt,
+    WasmPlugin,
+};
 
 const PLUGIN_BYTES: &[u8] = include_bytes!("../plugin.wasm");
 
@@ -12,6 +15,19 @@ extern "C" {
 
     pub fn greet(name: &String) -> String;
     pub fn sum_object(obj: &Object) -> f32;
+    // Errors returned by the plugin are passed to the host.
+    pub fn parse_number(text: &String) -> Result<i32, String>;
+    pub fn describe_fact(index: u32) -> String;
+    pub fn scale(value: f64, factor: f32, times: usize, shift: isize) -> f64;
+    pub fn count_items(items: &Vec<i32>) -> usize;
+    pub fn wide_sum(a: u128, b: i128) -> i128;
+    // Mutable references are written back after the call.
+    pub fn extend_list(list: &mut Vec<i32>, count: u32);
+    pub fn shout(name: &String) -> String;
+    // Byte slices and strings skip encoding.
+    pub fn checksum(data: &[u8]) -> u32;
+    pub fn invert(data: &[u8]) -> Vec<u8>;
+    pub fn reverse(text: &str) -> String;
 }
 
 // `i32` is the state type. You can skip it if you are not using state.
@@ -41,16 +57,55 @@ fn random_cat_fact() -> [String; 2] {
     get_random_cat_fact()
 }
 
+// Errors are delivered to the plugin, use `#[host_function(i32, trap)]` to trap instead.
+#[host_function(i32)]
+fn cat_fact(index: u32) -> Result<String, String> {
+    FACTS
+        .get(index as usize)
+        .map(|&fact| fact.to_owned())
+        .ok_or_else(|| format!("There is no fact number {index}"))
+}
+
+#[host_function(i32)]
+fn uppercase(text: &mut String) {
+    *text = text.to_uppercase();
+}
+
+#[host_function(i32)]
+fn log_line(line: &str) {
+    println!("Wasm log: {line}");
+}
+
 fn main() -> Result<()> {
     let plugin = WasmPlugin::builder()
         // Initial plugin state, host functions will have mutable access to it.
         .with_state(0)
         .from_binary(PLUGIN_BYTES)?
         // This makes `print` accessible to the plugin.
-        .with_imports(make_imports![print, random_cat_fact])
+        .with_imports(make_imports![
+            print,
+            random_cat_fact,
+            cat_fact,
+            uppercase,
+            log_line
+        ])
         // This will cache `add_up_list` in plugin exports.
         // Not necessery but preferred.
-        .with_exports(make_exports![add_up_list_renamed, greet, sum_object])
+        .with_exports(make_exports![
+            add_up_list_renamed,
+            greet,
+            sum_object,
+            parse_number,
+            describe_fact,
+            scale,
+            count_items,
+            wide_sum,
+            extend_list,
+            shout,
+            checksum,
+            invert,
+            reverse
+        ])
         .finish()?;
 
     // If we had't call `.with_exports(make_exports![add_up_list])` this would fail.
@@ -75,5 +130,47 @@ fn main() -> Result<()> {
     })?;
     assert_eq!(result, 15.3);
 
+    // `flatten_guest` combines plugin errors with call failures.
+    let number = plugin.function_unwrap::<parse_number>()(&"42".into()).flatten_guest()?;
+    assert_eq!(number, 42);
+
+    let error = plugin.function_unwrap::<parse_number>()(&"forty two".into()).flatten_guest();
+    assert!(matches!(error, Err(GuestError::Guest(_))));
+
+    let fact = plugin.function_unwrap::<describe_fact>()(0)?;
+    assert_eq!(fact, format!("Fact: {}", FACTS[0]));
+
+    let fact = plugin.function_unwrap::<describe_fact>()(10)?;
+    assert_eq!(fact, "Error: There is no fact number 10");
+
+    let scaled = plugin.function_unwrap::<scale>()(1.5, 2., 3, -1)?;
+    assert_eq!(scaled, 8.);
+
+    let count = plugin.function_unwrap::<count_items>()(&vec![1, 2, 3])?;
+    assert_eq!(count, 3);
+
+    let sum = plugin.function_unwrap::<wide_sum>()(u64::MAX as u128 + 1, -1)?;
+    assert_eq!(sum, u64::MAX as i128);
+
+    let mut list = vec![1, 2, 3];
+    plugin.function_unwrap::<extend_list>()(&mut list, 2)?;
+    assert_eq!(list, [1, 2, 3, 4, 5]);
+
+    let text = plugin.function_unwrap::<shout>()(&"Jack".into())?;
+    assert_eq!(text, "HELLO, JACK!");
+
+    let sum = plugin.function_unwrap::<checksum>()(&[1, 2, 3, 250])?;
+    assert_eq!(sum, 256);
+
+    let inverted = plugin.function_unwrap::<invert>()(&[0, 255, 15])?;
+    assert_eq!(inverted, [255, 0, 240]);
+
+    let reversed = plugin.function_unwrap::<reverse>()("Jack")?;
+    assert_eq!(reversed, "kcaJ");
+
+    // State can be read outside of calls as well.
+    let calls = *plugin.state::<i32>().unwrap();
+    println!("`print` was called {calls} times");
+
     Ok(())
 }
@@ -1,16 +1,120 @@
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
 use syn::{
-    parse_macro_input, parse_quote, Expr, FnArg, ForeignItem, ItemFn, ItemForeignMod, Pat,
-    ReturnType, Signature, Stmt, Type, TypeReference,
+    parse::ParseStream, parse_macro_input, parse_quote, punctuated::Punctuated, Expr, FnArg,
+    ForeignItem, GenericArgument, Ident, ItemFn, ItemForeignMod, Pat, Path, PathArguments,
+    ReturnType, Signature, Stmt, Token, Type, TypeReference,
 };
 
 fn is_atom_type(ty: &str) -> bool {
     const ATOMS: &[&str] = &[
-        "bool", "char", "u8", "u16", "u32", "u64", "i8", "i16", "i32", "i64",
+        "u8", "u16", "u32", "u64", "i8", "i16", "i32", "i64", "f32", "f64", "usize", "isize",
     ];
 
-    ATOMS.iter().any(|&a| a == ty)
+    ATOMS.contains(&ty)
+}
+
+/// `bool` and `char` are not wasm values, they are passed as `u32`.
+#[derive(Clone, Copy)]
+enum ScalarKind {
+    Bool,
+    Char,
+}
+
+fn scalar_kind(ty: &str) -> Option<ScalarKind> {
+    match ty {
+        "bool" => Some(ScalarKind::Bool),
+        "char" => Some(ScalarKind::Char),
+        _ => None,
+    }
+}
+
+impl ScalarKind {
+    /// Converts `u32` received from the host.
+    fn decode(self, value: &Ident) -> Expr {
+        match self {
+            ScalarKind::Bool => parse_quote!(#value != 0),
+            ScalarKind::Char => {
+                parse_quote!(char::from_u32(#value).expect("Guest was given invalid char"))
+            }
+        }
+    }
+}
+
+fn is_wide_type(ty: &str) -> bool {
+    ty == "u128" || ty == "i128"
+}
+
+/// Names of the pointer and value used to pass `&mut T`.
+fn mut_idents(name: &Ident) -> (Ident, Ident) {
+    (
+        format_ident!("__{name}_ptr"),
+        format_ident!("__{name}_value"),
+    )
+}
+
+/// Names of the halves `u128` and `i128` are split into.
+fn split_idents(name: &Ident) -> (Ident, Ident) {
+    (format_ident!("__{name}_lo"), format_ident!("__{name}_hi"))
+}
+
+#[derive(Clone, Copy)]
+enum BytesKind {
+    Bytes,
+    Str,
+}
+
+/// Detects `&[u8]` and `&str` parameters, or `Vec<u8>` and `String` return values,
+/// that are copied as raw bytes without encoding.
+fn bytes_kind(ty: &Type, allow_owned: bool) -> Option<BytesKind> {
+    fn last_segment(ty: &Type) -> Option<&syn::PathSegment> {
+        match ty {
+            Type::Path(path) if path.qself.is_none() => path.path.segments.last(),
+            _ => None,
+        }
+    }
+
+    fn is_u8(ty: &Type) -> bool {
+        last_segment(ty).is_some_and(|seg| seg.ident == "u8")
+    }
+
+    if allow_owned {
+        let seg = last_segment(ty)?;
+        if seg.ident == "String" {
+            return Some(BytesKind::Str);
+        }
+
+        let PathArguments::AngleBracketed(args) = &seg.arguments else {
+            return None;
+        };
+        return match args.args.first() {
+            Some(GenericArgument::Type(elem)) if seg.ident == "Vec" && is_u8(elem) => {
+                Some(BytesKind::Bytes)
+            }
+            _ => None,
+        };
+    }
+
+    match ty {
+        Type::Reference(TypeReference {
+            lifetime: None,
+            mutability: None,
+            elem,
+            ..
+        }) => match elem.as_ref() {
+            Type::Slice(slice) if is_u8(&slice.elem) => Some(BytesKind::Bytes),
+            elem if last_segment(elem).is_some_and(|seg| seg.ident == "str") => {
+                Some(BytesKind::Str)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Names of the offset and length raw bytes are passed as.
+fn bytes_idents(name: &Ident) -> (Ident, Ident) {
+    (format_ident!("__{name}_ptr"), format_ident!("__{name}_len"))
 }
 
 #[derive(Clone, Copy)]
@@ -20,34 +124,79 @@ enum WrapMode {
 }
 
 impl WrapMode {
-    fn wrap(self, ty: Type) -> Type {
+    fn wrap(self, ty: Type, codec: &Path) -> Type {
         match self {
-            WrapMode::Encoded => parse_quote!(scotch_guest::EncodedPtr<#ty>),
+            WrapMode::Encoded => parse_quote!(scotch_guest::EncodedPtr<#ty, #codec>),
+            WrapMode::Managed => parse_quote!(scotch_guest::MemoryType),
+        }
+    }
+
+    fn wrap_mut(self, ty: Type, codec: &Path) -> Type {
+        match self {
+            WrapMode::Encoded => parse_quote!(scotch_guest::EncodedMutPtr<#ty, #codec>),
             WrapMode::Managed => parse_quote!(scotch_guest::MemoryType),
         }
     }
 }
 
+/// Parses optional `codec = path` argument of the macros, defaults to bincode.
+fn parse_codec(input: ParseStream) -> syn::Result<Path> {
+    if input.is_empty() {
+        return Ok(parse_quote!(scotch_guest::Bincode));
+    }
+
+    let name: Ident = input.parse()?;
+    if name != "codec" {
+        return Err(syn::Error::new_spanned(name, "Expected `codec = path`"));
+    }
+    let _: Token![=] = input.parse()?;
+    input.parse()
+}
+
 enum TypeTranslation {
     Original,
+    /// `u128` or `i128` passed as two `u64`.
+    Split,
+    /// `bool` or `char` passed as `u32`.
+    Scalar(ScalarKind),
     Wrapped(Type),
+    /// `&mut T` that is written back after the call.
+    WrappedMut(Type),
+    /// `&[u8]`, `&str`, `Vec<u8>` or `String` copied without encoding.
+    Raw(BytesKind),
 }
 
-fn translate_type(ty: Type, mode: WrapMode, allow_owned: bool) -> TypeTranslation {
+fn translate_type(ty: Type, mode: WrapMode, codec: &Path, allow_owned: bool) -> TypeTranslation {
+    let name = match &ty {
+        Type::Path(path) => path.path.segments.last().unwrap().ident.to_string(),
+        _ => String::new(),
+    };
+
+    if let Some(kind) = bytes_kind(&ty, allow_owned) {
+        return TypeTranslation::Raw(kind);
+    }
+
     match ty {
-        Type::Path(ref path)
-            if is_atom_type(&path.path.segments.last().unwrap().ident.to_string()) =>
-        {
-            TypeTranslation::Original
+        Type::Path(_) if is_atom_type(&name) => TypeTranslation::Original,
+        Type::Path(_) if scalar_kind(&name).is_some() => {
+            TypeTranslation::Scalar(scalar_kind(&name).unwrap())
         }
+        // Multi-value returns are not available so wide integers are encoded when returned.
+        Type::Path(_) if is_wide_type(&name) && !allow_owned => TypeTranslation::Split,
         Type::Reference(TypeReference {
             lifetime: None,
             mutability: None,
             elem,
             ..
-        }) => TypeTranslation::Wrapped(mode.wrap(*elem)),
-        Type::Array(_) | Type::Tuple(_) => TypeTranslation::Wrapped(mode.wrap(ty)),
-        Type::Path(_) if allow_owned => TypeTranslation::Wrapped(mode.wrap(ty)),
+        }) => TypeTranslation::Wrapped(mode.wrap(*elem, codec)),
+        Type::Reference(TypeReference {
+            lifetime: None,
+            mutability: Some(_),
+            elem,
+            ..
+        }) if !allow_owned => TypeTranslation::WrappedMut(mode.wrap_mut(*elem, codec)),
+        Type::Array(_) | Type::Tuple(_) => TypeTranslation::Wrapped(mode.wrap(ty, codec)),
+        Type::Path(_) if allow_owned => TypeTranslation::Wrapped(mode.wrap(ty, codec)),
         _ => unimplemented!("Type is unsupported, consider using a reference instead."),
     }
 }
@@ -59,54 +208,124 @@ struct HostInputTranslation {
     epilogue: Vec<Stmt>,
 }
 
-fn translate_host_inputs<'a>(it: impl Iterator<Item = &'a mut FnArg>) -> HostInputTranslation {
+fn translate_host_inputs(
+    inputs: &mut Punctuated<FnArg, Token![,]>,
+    codec: &Path,
+) -> HostInputTranslation {
     let mut out = HostInputTranslation::default();
 
-    it.map(|arg| {
-        if let FnArg::Typed(arg) = arg {
-            arg
-        } else {
-            panic!("self is not allowed in host functions")
-        }
-    })
-    .map(|arg| {
-        if let Pat::Ident(name) = arg.pat.as_mut() {
-            (name.ident.clone(), &mut arg.ty)
-        } else {
-            panic!("Invalid function argument name")
-        }
-    })
-    .for_each(|(name, ty)| {
-        if let TypeTranslation::Wrapped(new) =
-            translate_type(ty.as_ref().clone(), WrapMode::Managed, false)
-        {
-            *ty = Box::new(new);
-            out.prelude
-                .push(parse_quote!(let #name = scotch_guest::ManagedPtr::new(#name).unwrap();));
-            out.epilogue.push(parse_quote!(#name.free();));
-            out.call_args.push(parse_quote!(#name.offset()));
-        } else {
-            out.call_args.push(parse_quote!(#name));
-        }
-    });
+    *inputs = std::mem::take(inputs)
+        .into_iter()
+        .map(|arg| {
+            if let FnArg::Typed(arg) = arg {
+                arg
+            } else {
+                panic!("self is not allowed in host functions")
+            }
+        })
+        .flat_map(|mut arg| {
+            let name = if let Pat::Ident(name) = arg.pat.as_ref() {
+                name.ident.clone()
+            } else {
+                panic!("Invalid function argument name")
+            };
+
+            match translate_type(arg.ty.as_ref().clone(), WrapMode::Managed, codec, false) {
+                TypeTranslation::Wrapped(new) => {
+                    *arg.ty = new;
+                    out.prelude.push(
+                        parse_quote!(let #name = scotch_guest::ManagedPtr::<_, #codec>::new(#name).unwrap();),
+                    );
+                    out.epilogue.push(parse_quote!(#name.free();));
+                    out.call_args.push(parse_quote!(#name.offset()));
+                    vec![FnArg::Typed(arg)]
+                }
+                TypeTranslation::WrappedMut(new) => {
+                    let (ptr, _) = mut_idents(&name);
+                    *arg.ty = new;
+                    out.prelude.push(
+                        parse_quote!(let #ptr = scotch_guest::ManagedMutPtr::<_, #codec>::new(&*#name).unwrap();),
+                    );
+                    out.epilogue.push(parse_quote! {
+                        *#name = #ptr.read_back().expect("Guest received invalid ptr");
+                    });
+                    out.call_args.push(parse_quote!(#ptr.offset()));
+                    vec![FnArg::Typed(arg)]
+                }
+                TypeTranslation::Scalar(_) => {
+                    out.call_args.push(parse_quote!(#name as u32));
+                    *arg.ty = parse_quote!(u32);
+                    vec![FnArg::Typed(arg)]
+                }
+                TypeTranslation::Split => {
+                    let (lo, hi) = split_idents(&name);
+                    out.call_args.push(parse_quote!(#name as u128 as u64));
+                    out.call_args
+                        .push(parse_quote!(((#name as u128) >> 64) as u64));
+                    vec![parse_quote!(#lo: u64), parse_quote!(#hi: u64)]
+                }
+                TypeTranslation::Raw(_) => {
+                    // Host copies the bytes itself, so they are passed in place.
+                    let (ptr, len) = bytes_idents(&name);
+                    out.call_args.push(parse_quote!(
+                        AsRef::<[u8]>::as_ref(#name).as_ptr() as scotch_guest::MemoryType
+                    ));
+                    out.call_args.push(parse_quote!(
+                        AsRef::<[u8]>::as_ref(#name).len() as scotch_guest::MemoryType
+                    ));
+                    vec![
+                        parse_quote!(#ptr: scotch_guest::MemoryType),
+                        parse_quote!(#len: scotch_guest::MemoryType),
+                    ]
+                }
+                TypeTranslation::Original => {
+                    out.call_args.push(parse_quote!(#name));
+                    vec![FnArg::Typed(arg)]
+                }
+            }
+        })
+        .collect();
 
     out
 }
 
-fn translate_host_output(ret: &mut ReturnType) -> Stmt {
+fn translate_host_output(ret: &mut ReturnType, codec: &Path) -> Stmt {
     let mut out = parse_quote!(return out;);
 
     if let ReturnType::Type(_, ty) = ret {
-        if let TypeTranslation::Wrapped(new) =
-            translate_type(ty.as_ref().clone(), WrapMode::Managed, true)
-        {
-            *ty = Box::new(new);
-            out = parse_quote! {return {
-                let ptr = scotch_guest::ManagedPtr::with_size_by_address(out);
-                let value = ptr.read().expect("Guest received invalid ptr");
-                ptr.free();
-                value
-            };};
+        match translate_type(ty.as_ref().clone(), WrapMode::Managed, codec, true) {
+            TypeTranslation::Wrapped(new) => {
+                **ty = new;
+                out = parse_quote! {return {
+                    let ptr = scotch_guest::ManagedPtr::<_, #codec>::with_size_by_address(out)
+                        .expect("Guest received invalid ptr");
+                    let value = ptr.read().expect("Guest received invalid ptr");
+                    ptr.free();
+                    value
+                };};
+            }
+            TypeTranslation::Scalar(kind) => {
+                **ty = parse_quote!(u32);
+                let convert = kind.decode(&format_ident!("out"));
+                out = parse_quote!(return #convert;);
+            }
+            TypeTranslation::Raw(kind) => {
+                **ty = parse_quote!(scotch_guest::MemoryType);
+                let read: Expr = match kind {
+                    BytesKind::Bytes => parse_quote!(bytes.read_bytes()),
+                    BytesKind::Str => {
+                        parse_quote!(bytes.read_string().expect("Guest received invalid string"))
+                    }
+                };
+                out = parse_quote! {return {
+                    let bytes = scotch_guest::ManagedBytes::with_size_by_address(out)
+                        .expect("Guest received invalid ptr");
+                    let value = #read;
+                    bytes.free();
+                    value
+                };};
+            }
+            _ => (),
         }
     }
 
@@ -120,8 +339,14 @@ fn translate_host_output(ret: &mut ReturnType) -> Stmt {
 ///     fn print(val: &String);
 /// }
 /// ```
+/// Host functions that return `Result<T, E>` are declared with the same return type,
+/// unless they were marked with `trap` on the host, in which case the return type is `T`.
+///
+/// Values are encoded with `scotch_guest::Bincode` unless another codec is given,
+/// e.g. `#[scotch_guest::host_functions(codec = scotch_guest::Postcard)]`.
 #[proc_macro_attribute]
-pub fn host_functions(_: TokenStream, input: TokenStream) -> TokenStream {
+pub fn host_functions(args: TokenStream, input: TokenStream) -> TokenStream {
+    let codec = parse_macro_input!(args with parse_codec);
     let host_funcs = parse_macro_input!(input as ItemForeignMod);
     let funcs = host_funcs
         .items
@@ -143,7 +368,7 @@ pub fn host_functions(_: TokenStream, input: TokenStream) -> TokenStream {
             } = func.sig.clone();
 
             let sig = &mut func.sig;
-            let ending = translate_host_output(&mut sig.output);
+            let ending = translate_host_output(&mut sig.output, &codec);
 
             let fake_id = format_ident!("_host_{}", sig.ident);
             sig.ident = fake_id.clone();
@@ -152,7 +377,7 @@ pub fn host_functions(_: TokenStream, input: TokenStream) -> TokenStream {
                 prelude,
                 epilogue,
                 call_args,
-            } = translate_host_inputs(sig.inputs.iter_mut());
+            } = translate_host_inputs(&mut sig.inputs, &codec);
 
             quote! {
                 fn #ident(#inputs) #output {
@@ -181,36 +406,87 @@ pub fn host_functions(_: TokenStream, input: TokenStream) -> TokenStream {
 #[derive(Default)]
 struct GuestInputTranslation {
     prelude: Vec<Stmt>,
+    post: Vec<Stmt>,
 }
 
-fn translate_guest_inputs<'a>(it: impl Iterator<Item = &'a mut FnArg>) -> GuestInputTranslation {
+fn translate_guest_inputs(
+    inputs: &mut Punctuated<FnArg, Token![,]>,
+    codec: &Path,
+) -> GuestInputTranslation {
     let mut out = GuestInputTranslation::default();
 
-    it.map(|arg| {
+    *inputs = std::mem::take(inputs).into_iter().map(|arg| {
         let FnArg::Typed(arg) = arg else { panic!("self is not allowed in guest functions") };
         let Pat::Ident(id) = &*arg.pat else { panic!("Invalid function declation") };
-        (id.ident.clone(), &mut arg.ty)
+        (id.ident.clone(), arg)
     })
-    .for_each(|(name, ty)| {
-        if let TypeTranslation::Wrapped(new) = translate_type(ty.as_ref().clone(), WrapMode::Encoded, false) {
-            out.prelude
-                .push(parse_quote!(let #name: #ty = &unsafe { #name.read().expect("Guest was given invalid pointer") };));
-            *ty = Box::new(new);
-        };
-    });
+    .flat_map(|(name, mut arg)| {
+        let ty = arg.ty.as_ref().clone();
+        match translate_type(ty.clone(), WrapMode::Encoded, codec, false) {
+            TypeTranslation::Wrapped(new) => {
+                out.prelude
+                    .push(parse_quote!(let #name: #ty = &unsafe { #name.read().expect("Guest was given invalid pointer") };));
+                *arg.ty = new;
+                vec![FnArg::Typed(arg)]
+            }
+            TypeTranslation::WrappedMut(new) => {
+                let (ptr, value) = mut_idents(&name);
+                out.prelude.extend::<[Stmt; 3]>([
+                    parse_quote!(let #ptr = #name;),
+                    parse_quote!(let mut #value = unsafe { #ptr.read().expect("Guest was given invalid pointer") };),
+                    parse_quote!(let #name: #ty = &mut #value;),
+                ]);
+                out.post.push(parse_quote!(unsafe { #ptr.write_back(&#value).expect("Failed to write back mutable argument") };));
+                *arg.ty = new;
+                vec![FnArg::Typed(arg)]
+            }
+            TypeTranslation::Scalar(kind) => {
+                let convert = kind.decode(&name);
+                out.prelude.push(parse_quote!(let #name: #ty = #convert;));
+                *arg.ty = parse_quote!(u32);
+                vec![FnArg::Typed(arg)]
+            }
+            TypeTranslation::Split => {
+                let (lo, hi) = split_idents(&name);
+                out.prelude.push(parse_quote!(let #name = (((#hi as u128) << 64) | #lo as u128) as #ty;));
+                vec![parse_quote!(#lo: u64), parse_quote!(#hi: u64)]
+            }
+            TypeTranslation::Raw(kind) => {
+                let (ptr, len) = bytes_idents(&name);
+                out.prelude.push(match kind {
+                    BytesKind::Bytes => parse_quote!(let #name: #ty = unsafe { scotch_guest::slice_from_parts(#ptr, #len) };),
+                    BytesKind::Str => parse_quote!(let #name: #ty = unsafe { scotch_guest::str_from_parts(#ptr, #len).expect("Guest was given invalid string") };),
+                });
+                vec![parse_quote!(#ptr: scotch_guest::MemoryType), parse_quote!(#len: scotch_guest::MemoryType)]
+            }
+            TypeTranslation::Original => vec![FnArg::Typed(arg)],
+        }
+    })
+    .collect();
 
     out
 }
 
-fn translate_guest_output(ret: &mut ReturnType) -> Stmt {
+fn translate_guest_output(ret: &mut ReturnType, codec: &Path) -> Stmt {
     let mut out = parse_quote!(return out;);
 
     if let ReturnType::Type(_, ty) = ret {
-        if let TypeTranslation::Wrapped(new) =
-            translate_type(ty.as_ref().clone(), WrapMode::Managed, true)
-        {
-            *ty = Box::new(new);
-            out = parse_quote!(return scotch_guest::ManagedPtr::new(&out).unwrap().offset(););
+        match translate_type(ty.as_ref().clone(), WrapMode::Managed, codec, true) {
+            TypeTranslation::Wrapped(new) => {
+                **ty = new;
+                out = parse_quote!(return scotch_guest::ManagedPtr::<_, #codec>::new(&out).unwrap().offset(););
+            }
+            TypeTranslation::Scalar(_) => {
+                **ty = parse_quote!(u32);
+                out = parse_quote!(return out as u32;);
+            }
+            TypeTranslation::Raw(_) => {
+                **ty = parse_quote!(scotch_guest::MemoryType);
+                out = parse_quote! {
+                    return scotch_guest::ManagedBytes::new(AsRef::<[u8]>::as_ref(&out)).offset();
+                };
+            }
+            _ => (),
         }
     }
 
@@ -224,20 +500,33 @@ fn translate_guest_output(ret: &mut ReturnType) -> Stmt {
 ///     items.iter().sum::<i32>()
 /// }
 /// ```
+/// Guest functions can also return `Result<T, E>` where both `T` and `E` implement
+/// `Encode` and `Decode`, errors are then delivered to the host instead of trapping.
+/// ```ignore
+/// #[scotch_guest::guest_function]
+/// fn parse_number(text: &String) -> Result<i32, String> {
+///     text.parse().map_err(|e: core::num::ParseIntError| e.to_string())
+/// }
+/// ```
+/// Values are encoded with `scotch_guest::Bincode` unless another codec is given,
+/// e.g. `#[scotch_guest::guest_function(codec = scotch_guest::Postcard)]`.
 #[proc_macro_attribute]
-pub fn guest_function(_: TokenStream, input: TokenStream) -> TokenStream {
+pub fn guest_function(args: TokenStream, input: TokenStream) -> TokenStream {
+    let codec = parse_macro_input!(args with parse_codec);
     let mut item_fn = parse_macro_input!(input as ItemFn);
     item_fn.attrs.push(parse_quote!(#[no_mangle]));
     item_fn.sig.abi = Some(parse_quote!(extern "C"));
 
-    let GuestInputTranslation { prelude } = translate_guest_inputs(item_fn.sig.inputs.iter_mut());
+    let GuestInputTranslation { prelude, post } =
+        translate_guest_inputs(&mut item_fn.sig.inputs, &codec);
     let output = item_fn.sig.output.clone();
-    let epilogue = translate_guest_output(&mut item_fn.sig.output);
+    let epilogue = translate_guest_output(&mut item_fn.sig.output, &codec);
     let body = item_fn.block;
 
     item_fn.block = parse_quote!({
         #(#prelude)*
         let out = (move || #output #body)();
+        #(#post)*
         #epilogue
     });
 
@@ -0,0 +1,103 @@
+extern crate alloc;
+
+use crate::{
+    header::{Header, HEADER_SIZE},
+    MemoryType,
+};
+use alloc::{string::String, vec::Vec};
+use bincode::error::DecodeError;
+use core::{
+    alloc::Layout,
+    slice::from_raw_parts,
+    str::{from_utf8, Utf8Error},
+};
+
+/// Raw bytes created by the guest without encoding.
+#[doc(hidden)]
+pub struct ManagedBytes {
+    offset: MemoryType,
+    size: usize,
+}
+
+impl ManagedBytes {
+    #[inline(always)]
+    pub fn offset(&self) -> MemoryType {
+        self.offset
+    }
+
+    pub fn new(bytes: &[u8]) -> Self {
+        unsafe {
+            let layout = Layout::from_size_align(bytes.len() + HEADER_SIZE, 1).unwrap();
+            let ptr = alloc::alloc::alloc(layout);
+            if ptr.is_null() {
+                alloc::alloc::handle_alloc_error(layout);
+            }
+
+            ptr.copy_from_nonoverlapping(Header::new(bytes.len()).to_bytes().as_ptr(), HEADER_SIZE);
+            ptr.add(HEADER_SIZE)
+                .copy_from_nonoverlapping(bytes.as_ptr(), bytes.len());
+
+            Self {
+                offset: ptr as MemoryType,
+                size: bytes.len(),
+            }
+        }
+    }
+
+    pub fn with_size_by_address(addr: MemoryType) -> Result<Self, DecodeError> {
+        unsafe {
+            Ok(Self {
+                size: Header::read(addr as _)?.len as _,
+                offset: addr,
+            })
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { from_raw_parts((self.offset as usize + HEADER_SIZE) as _, self.size) }
+    }
+
+    pub fn read_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    pub fn read_string(&self) -> Result<String, DecodeError> {
+        from_utf8(self.as_bytes())
+            .map(String::from)
+            .map_err(utf8_error)
+    }
+
+    pub fn free(self) {
+        unsafe {
+            alloc::alloc::dealloc(
+                self.offset as _,
+                Layout::from_size_align(self.size + HEADER_SIZE, 1).unwrap(),
+            );
+        }
+    }
+}
+
+/// Borrows bytes that host has copied to the guest memory.
+/// # Safety
+/// Bytes were passed by scotch_host and are valid for `'a`.
+#[doc(hidden)]
+#[inline]
+pub unsafe fn slice_from_parts<'a>(offset: MemoryType, len: MemoryType) -> &'a [u8] {
+    from_raw_parts(offset as _, len as _)
+}
+
+/// Borrows a string that host has copied to the guest memory.
+/// # Safety
+/// Bytes were passed by scotch_host and are valid for `'a`.
+#[doc(hidden)]
+#[inline]
+pub unsafe fn str_from_parts<'a>(
+    offset: MemoryType,
+    len: MemoryType,
+) -> Result<&'a str, DecodeError> {
+    from_utf8(slice_from_parts(offset, len)).map_err(utf8_error)
+}
+
+fn utf8_error(inner: Utf8Error) -> DecodeError {
+    DecodeError::Utf8 { inner }
+}
@@ -0,0 +1,130 @@
+extern crate alloc;
+
+use alloc::vec::Vec;
+use bincode::error::{DecodeError, EncodeError};
+
+#[cfg(any(feature = "postcard", feature = "json"))]
+use alloc::string::ToString;
+
+/// Identifies the format a plugin encodes values with, see [`Codec::ID`].
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodecId {
+    Bincode = 0,
+    Postcard = 1,
+    Json = 2,
+}
+
+/// Format used to encode values passed between host and guest.
+/// Codec is a type parameter of the macros, [`Bincode`] is used by default.
+/// ```ignore
+/// scotch_guest::export_alloc!(codec = scotch_guest::Postcard);
+///
+/// #[scotch_guest::guest_function(codec = scotch_guest::Postcard)]
+/// fn add_up_list(items: &Vec<i32>) -> i32 {
+///     items.iter().sum::<i32>()
+/// }
+/// ```
+/// Host and guest must use the same codec.
+pub trait Codec: 'static {
+    /// Id reported to the host by `export_alloc!`.
+    const ID: CodecId;
+}
+
+/// Codec that can encode and decode `T`.
+pub trait Encodes<T>: Codec {
+    /// Encodes `value` into `buf`, returning the number of bytes written.
+    /// Fails if `buf` is too small.
+    fn encode_into_slice(value: &T, buf: &mut [u8]) -> Result<usize, EncodeError>;
+
+    fn encode_to_vec(value: &T) -> Result<Vec<u8>, EncodeError>;
+
+    fn decode_from_slice(bytes: &[u8]) -> Result<T, DecodeError>;
+}
+
+/// Types that can be passed between host and guest with the [`Bincode`] codec.
+/// Requires `bincode::Encode` and `bincode::Decode`, serde types can be wrapped
+/// into [`Serde`] with `serde` feature.
+pub trait Value: bincode::Encode + bincode::Decode {}
+
+impl<T: bincode::Encode + bincode::Decode> Value for T {}
+
+/// Wrapper that encodes serde types with the `bincode` codec, so they can be passed
+/// next to types that implement `bincode::Encode` and `bincode::Decode`.
+/// ```ignore
+/// fn greet(person: &Serde<Person>) -> String;
+/// ```
+#[cfg(feature = "serde")]
+pub use bincode::serde::Compat as Serde;
+
+/// Encodes [`Value`]s with `bincode`, the default codec.
+pub struct Bincode;
+
+impl Codec for Bincode {
+    const ID: CodecId = CodecId::Bincode;
+}
+
+impl<T: Value> Encodes<T> for Bincode {
+    fn encode_into_slice(value: &T, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        bincode::encode_into_slice(value, buf, bincode::config::standard())
+    }
+
+    fn encode_to_vec(value: &T) -> Result<Vec<u8>, EncodeError> {
+        bincode::encode_to_vec(value, bincode::config::standard())
+    }
+
+    fn decode_from_slice(bytes: &[u8]) -> Result<T, DecodeError> {
+        bincode::decode_from_slice(bytes, bincode::config::standard()).map(|(val, _)| val)
+    }
+}
+
+/// Encodes serde types with `postcard`.
+#[cfg(feature = "postcard")]
+pub struct Postcard;
+
+#[cfg(feature = "postcard")]
+impl Codec for Postcard {
+    const ID: CodecId = CodecId::Postcard;
+}
+
+#[cfg(feature = "postcard")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> Encodes<T> for Postcard {
+    fn encode_into_slice(value: &T, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        postcard::to_slice(value, buf)
+            .map(|out| out.len())
+            .map_err(|e| EncodeError::OtherString(e.to_string()))
+    }
+
+    fn encode_to_vec(value: &T) -> Result<Vec<u8>, EncodeError> {
+        postcard::to_allocvec(value).map_err(|e| EncodeError::OtherString(e.to_string()))
+    }
+
+    fn decode_from_slice(bytes: &[u8]) -> Result<T, DecodeError> {
+        postcard::from_bytes(bytes).map_err(|e| DecodeError::OtherString(e.to_string()))
+    }
+}
+
+/// Encodes serde types with `serde_json`.
+#[cfg(feature = "json")]
+pub struct Json;
+
+#[cfg(feature = "json")]
+impl Codec for Json {
+    const ID: CodecId = CodecId::Json;
+}
+
+#[cfg(feature = "json")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> Encodes<T> for Json {
+    // serde_json can not write into a slice without std, values are always encoded to a vec.
+    fn encode_into_slice(_: &T, _: &mut [u8]) -> Result<usize, EncodeError> {
+        Err(EncodeError::UnexpectedEnd)
+    }
+
+    fn encode_to_vec(value: &T) -> Result<Vec<u8>, EncodeError> {
+        serde_json::to_vec(value).map_err(|e| EncodeError::OtherString(e.to_string()))
+    }
+
+    fn decode_from_slice(bytes: &[u8]) -> Result<T, DecodeError> {
+        serde_json::from_slice(bytes).map_err(|e| DecodeError::OtherString(e.to_string()))
+    }
+}
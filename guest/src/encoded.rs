@@ -1,24 +1,61 @@
-use crate::MemoryType;
-use bincode::{config::standard, error::DecodeError, Decode, Encode};
+use crate::{
+    header::{read_cell, write_cell, Header, HEADER_SIZE},
+    Bincode, Encodes, ManagedPtr, MemoryType,
+};
+use bincode::error::{DecodeError, EncodeError};
 use core::{marker::PhantomData, slice::from_raw_parts};
 
 #[repr(transparent)]
 #[doc(hidden)]
-pub struct EncodedPtr<T: Encode + Decode> {
+pub struct EncodedPtr<T, C = Bincode> {
     offset: MemoryType,
-    _ty: PhantomData<T>,
+    _ty: PhantomData<(T, C)>,
 }
 
-impl<T: Encode + Decode> EncodedPtr<T> {
+impl<T, C: Encodes<T>> EncodedPtr<T, C> {
     /// # Safety
     /// Pointer is managed by scotch_host and was not created by other means.
     #[inline]
     pub unsafe fn read(&self) -> Result<T, DecodeError> {
-        let mut size = [0, 0];
-        (self.offset as *const u8).copy_to_nonoverlapping(&mut size as _, 2);
+        let len = Header::read(self.offset as _)?.len as usize;
+        C::decode_from_slice(from_raw_parts(
+            (self.offset as usize + HEADER_SIZE) as _,
+            len,
+        ))
+    }
+}
+
+/// Pointer to a cell with the address of an envelope, used for `&mut T` arguments.
+#[repr(transparent)]
+#[doc(hidden)]
+pub struct EncodedMutPtr<T, C = Bincode> {
+    offset: MemoryType,
+    _ty: PhantomData<(T, C)>,
+}
+
+impl<T, C: Encodes<T>> EncodedMutPtr<T, C> {
+    /// # Safety
+    /// Pointer is managed by scotch_host and was not created by other means.
+    #[inline]
+    pub unsafe fn read(&self) -> Result<T, DecodeError> {
+        EncodedPtr::<T, C> {
+            offset: read_cell(self.offset as _) as MemoryType,
+            _ty: PhantomData,
+        }
+        .read()
+    }
+
+    /// Replaces the value in the cell so the host can read it after the call.
+    /// # Safety
+    /// Pointer is managed by scotch_host and was not created by other means.
+    pub unsafe fn write_back(&self, value: &T) -> Result<(), EncodeError> {
+        let old = ManagedPtr::<T, C>::with_size_by_address(read_cell(self.offset as _) as _)
+            .map_err(|_| EncodeError::Other("Invalid envelope header"))?;
+        let new = ManagedPtr::<T, C>::new(value)?;
+
+        write_cell(self.offset as _, new.offset() as _);
+        old.free();
 
-        let len = u16::from_le_bytes(size) as usize;
-        bincode::decode_from_slice(from_raw_parts((self.offset + 2) as _, len), standard())
-            .map(|(a, _)| a)
+        Ok(())
     }
 }
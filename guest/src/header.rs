@@ -0,0 +1,86 @@
+extern crate alloc;
+
+use alloc::string::ToString;
+use bincode::error::DecodeError;
+use core::ptr::copy_nonoverlapping;
+
+/// Magic bytes every envelope starts with.
+const MAGIC: [u8; 2] = *b"SC";
+
+/// Version of the envelope layout. Must match with `scotch_host::ENVELOPE_VERSION`.
+pub const ENVELOPE_VERSION: u8 = 1;
+
+/// Size of the header that precedes every value in linear memory.
+pub(crate) const HEADER_SIZE: usize = 12;
+
+/// Size of the cell that holds an address of an envelope as `u64`.
+pub(crate) const CELL_SIZE: usize = 8;
+
+/// # Safety
+/// `cell` must point to [`CELL_SIZE`] readable bytes.
+pub(crate) unsafe fn read_cell(cell: usize) -> usize {
+    let mut buf = [0; CELL_SIZE];
+    copy_nonoverlapping(cell as *const u8, buf.as_mut_ptr(), CELL_SIZE);
+    u64::from_le_bytes(buf) as usize
+}
+
+/// # Safety
+/// `cell` must point to [`CELL_SIZE`] writable bytes.
+pub(crate) unsafe fn write_cell(cell: usize, addr: usize) {
+    copy_nonoverlapping(
+        (addr as u64).to_le_bytes().as_ptr(),
+        cell as *mut u8,
+        CELL_SIZE,
+    );
+}
+
+/// Header that precedes every value passed between host and guest.
+/// See `scotch_host` for the layout description.
+#[derive(Clone, Copy)]
+pub(crate) struct Header {
+    pub(crate) len: u64,
+}
+
+impl Header {
+    #[inline]
+    pub(crate) fn new(len: usize) -> Self {
+        Self { len: len as u64 }
+    }
+
+    pub(crate) fn to_bytes(self) -> [u8; HEADER_SIZE] {
+        let mut out = [0; HEADER_SIZE];
+        out[..2].copy_from_slice(&MAGIC);
+        out[2] = ENVELOPE_VERSION;
+        out[4..].copy_from_slice(&self.len.to_le_bytes());
+        out
+    }
+
+    pub(crate) fn from_bytes(bytes: [u8; HEADER_SIZE]) -> Result<Self, DecodeError> {
+        if bytes[..2] != MAGIC {
+            return Err(DecodeError::OtherString(
+                "Invalid envelope header".to_string(),
+            ));
+        }
+
+        if bytes[2] != ENVELOPE_VERSION {
+            return Err(DecodeError::OtherString(
+                "Envelope version mismatch between host and guest".to_string(),
+            ));
+        }
+
+        let mut len = [0; 8];
+        len.copy_from_slice(&bytes[4..]);
+
+        Ok(Self {
+            len: u64::from_le_bytes(len),
+        })
+    }
+
+    /// # Safety
+    /// `addr` must point to at least [`HEADER_SIZE`] readable bytes.
+    pub(crate) unsafe fn read(addr: usize) -> Result<Self, DecodeError> {
+        let mut buf = [0; HEADER_SIZE];
+        copy_nonoverlapping(addr as *const u8, buf.as_mut_ptr(), HEADER_SIZE);
+        Self::from_bytes(buf)
+    }
+}
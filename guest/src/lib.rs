@@ -8,7 +8,17 @@ pub type MemoryType = u32;
 #[cfg(feature = "mem64")]
 pub type MemoryType = u64;
 
-type PrefixType = u16;
+mod header;
+pub use header::ENVELOPE_VERSION;
+
+mod codec;
+#[cfg(feature = "json")]
+pub use codec::Json;
+#[cfg(feature = "postcard")]
+pub use codec::Postcard;
+#[cfg(feature = "serde")]
+pub use codec::Serde;
+pub use codec::{Bincode, Codec, CodecId, Encodes, Value};
 
 mod encoded;
 pub use encoded::*;
@@ -16,15 +26,26 @@ pub use encoded::*;
 mod managed;
 pub use managed::*;
 
+mod bytes;
+pub use bytes::*;
+
 pub use scotch_guest_macros::*;
 
 /// Includes allocation utils for the host. Plugin will not work without it.
 /// You need to put it somewhere in your plugin crate.
+/// Plugins that encode values with another codec than [`Bincode`] report it to the host
+/// with `export_alloc!(codec = scotch_guest::Postcard)`.
 #[macro_export]
 macro_rules! export_alloc {
     () => {
+        $crate::export_alloc!(codec = $crate::Bincode);
+    };
+    (codec = $codec:path) => {
         #[no_mangle]
-        extern "C" fn __scotch_alloc(size: u32, align: u32) -> u32 {
+        extern "C" fn __scotch_alloc(
+            size: $crate::MemoryType,
+            align: $crate::MemoryType,
+        ) -> $crate::MemoryType {
             extern crate alloc;
             use alloc::alloc as a;
 
@@ -32,7 +53,16 @@ macro_rules! export_alloc {
         }
 
         #[no_mangle]
-        extern "C" fn __scotch_free(ptr: u32, size: u32, align: u32) {
+        extern "C" fn __scotch_codec() -> u32 {
+            <$codec as $crate::Codec>::ID as u32
+        }
+
+        #[no_mangle]
+        extern "C" fn __scotch_free(
+            ptr: $crate::MemoryType,
+            size: $crate::MemoryType,
+            align: $crate::MemoryType,
+        ) {
             extern crate alloc;
             use alloc::alloc as a;
 
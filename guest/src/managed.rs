@@ -1,51 +1,43 @@
 extern crate alloc;
 
-use crate::{MemoryType, PrefixType};
-use alloc::borrow::Cow;
-use bincode::{
-    config::standard,
-    error::{DecodeError, EncodeError},
-    Decode, Encode,
+use crate::{
+    header::{read_cell, write_cell, Header, CELL_SIZE, HEADER_SIZE},
+    Bincode, Encodes, MemoryType,
 };
-use core::{alloc::Layout, marker::PhantomData, mem::size_of, slice::from_raw_parts};
+use alloc::borrow::Cow;
+use bincode::error::{DecodeError, EncodeError};
+use core::{alloc::Layout, marker::PhantomData, slice::from_raw_parts};
 
 #[allow(dead_code)]
 #[doc(hidden)]
-pub struct ManagedPtr<T: Encode + Decode> {
+pub struct ManagedPtr<T, C = Bincode> {
     offset: MemoryType,
     size: usize,
-    _ty: PhantomData<T>,
+    _ty: PhantomData<(T, C)>,
 }
 
-impl<T: Encode + Decode> ManagedPtr<T> {
+impl<T, C: Encodes<T>> ManagedPtr<T, C> {
     #[inline(always)]
     pub fn offset(&self) -> MemoryType {
         self.offset
     }
 
-    pub fn with_size_by_address(addr: MemoryType) -> Self {
-        let mut buf = [0; size_of::<PrefixType>()];
+    pub fn with_size_by_address(addr: MemoryType) -> Result<Self, DecodeError> {
         unsafe {
-            buf.as_mut_ptr()
-                .copy_from_nonoverlapping(addr as _, size_of::<PrefixType>());
-            Self {
-                size: PrefixType::from_le_bytes(buf) as _,
+            Ok(Self {
+                size: Header::read(addr as _)?.len as _,
                 offset: addr,
                 _ty: PhantomData,
-            }
+            })
         }
     }
 
     pub fn read(&self) -> Result<T, DecodeError> {
         unsafe {
-            bincode::decode_from_slice(
-                from_raw_parts(
-                    (self.offset as usize + size_of::<PrefixType>()) as _,
-                    self.size,
-                ),
-                standard(),
-            )
-            .map(|(val, _)| val)
+            C::decode_from_slice(from_raw_parts(
+                (self.offset as usize + HEADER_SIZE) as _,
+                self.size,
+            ))
         }
     }
 
@@ -53,22 +45,21 @@ impl<T: Encode + Decode> ManagedPtr<T> {
         extern crate alloc;
 
         let mut buf = [0u8; 64];
-        let buf: Cow<[u8]> =
-            if let Ok(size) = bincode::encode_into_slice(value, &mut buf, standard()) {
-                Cow::Borrowed(&buf[..size])
-            } else {
-                Cow::Owned(bincode::encode_to_vec(value, standard())?)
-            };
+        let buf: Cow<[u8]> = if let Ok(size) = C::encode_into_slice(value, &mut buf) {
+            Cow::Borrowed(&buf[..size])
+        } else {
+            Cow::Owned(C::encode_to_vec(value)?)
+        };
 
         unsafe {
-            let ptr = alloc::alloc::alloc(
-                Layout::from_size_align(buf.len() + size_of::<PrefixType>(), 1).unwrap(),
-            );
-            ptr.copy_from_nonoverlapping(
-                (buf.len() as PrefixType).to_le_bytes().as_ptr(),
-                size_of::<PrefixType>(),
-            );
-            ptr.add(size_of::<PrefixType>())
+            let layout = Layout::from_size_align(buf.len() + HEADER_SIZE, 1).unwrap();
+            let ptr = alloc::alloc::alloc(layout);
+            if ptr.is_null() {
+                alloc::alloc::handle_alloc_error(layout);
+            }
+
+            ptr.copy_from_nonoverlapping(Header::new(buf.len()).to_bytes().as_ptr(), HEADER_SIZE);
+            ptr.add(HEADER_SIZE)
                 .copy_from_nonoverlapping(buf.as_ptr(), buf.len());
 
             Ok(Self {
@@ -83,8 +74,56 @@ impl<T: Encode + Decode> ManagedPtr<T> {
         unsafe {
             alloc::alloc::dealloc(
                 self.offset as _,
-                Layout::from_size_align(self.size, 1).unwrap(),
+                Layout::from_size_align(self.size + HEADER_SIZE, 1).unwrap(),
+            );
+        }
+    }
+}
+
+/// Pointer to a cell with the address of an envelope, used to pass `&mut T` to the host.
+#[doc(hidden)]
+pub struct ManagedMutPtr<T, C = Bincode> {
+    offset: MemoryType,
+    _ty: PhantomData<(T, C)>,
+}
+
+impl<T, C: Encodes<T>> ManagedMutPtr<T, C> {
+    #[inline(always)]
+    pub fn offset(&self) -> MemoryType {
+        self.offset
+    }
+
+    pub fn new(value: &T) -> Result<Self, EncodeError> {
+        let ptr = ManagedPtr::<T, C>::new(value)?;
+
+        unsafe {
+            let layout = Layout::from_size_align(CELL_SIZE, 1).unwrap();
+            let cell = alloc::alloc::alloc(layout);
+            if cell.is_null() {
+                alloc::alloc::handle_alloc_error(layout);
+            }
+
+            write_cell(cell as _, ptr.offset() as _);
+            Ok(Self {
+                offset: cell as MemoryType,
+                _ty: PhantomData,
+            })
+        }
+    }
+
+    /// Reads the value host has written back and frees both the envelope and the cell.
+    pub fn read_back(self) -> Result<T, DecodeError> {
+        unsafe {
+            let ptr = ManagedPtr::<T, C>::with_size_by_address(read_cell(self.offset as _) as _);
+            alloc::alloc::dealloc(
+                self.offset as _,
+                Layout::from_size_align(CELL_SIZE, 1).unwrap(),
             );
+
+            let ptr = ptr?;
+            let value = ptr.read();
+            ptr.free();
+            value
         }
     }
 }
@@ -5,16 +5,130 @@ use syn::{
     parse::{Parse, ParseStream, Parser},
     parse_macro_input, parse_quote,
     punctuated::Punctuated,
-    BareFnArg, FnArg, ForeignItem, ForeignItemFn, Ident, ItemFn, ItemForeignMod, Pat, PatType,
-    Path, ReturnType, Stmt, Token, Type, TypeBareFn, TypeReference, Visibility,
+    BareFnArg, Expr, FnArg, ForeignItem, ForeignItemFn, GenericArgument, Ident, ItemFn,
+    ItemForeignMod, Pat, PatType, Path, PathArguments, ReturnType, Stmt, Token, Type, TypeBareFn,
+    TypeReference, Visibility,
 };
 
 fn is_atom_type(ty: &str) -> bool {
     const ATOMS: &[&str] = &[
-        "bool", "char", "u8", "u16", "u32", "u64", "i8", "i16", "i32", "i64",
+        "u8", "u16", "u32", "u64", "i8", "i16", "i32", "i64", "f32", "f64",
     ];
 
-    ATOMS.iter().any(|&a| a == ty)
+    ATOMS.contains(&ty)
+}
+
+/// `bool` and `char` are not wasm values, they are passed as `u32`.
+#[derive(Clone, Copy)]
+enum ScalarKind {
+    Bool,
+    Char,
+}
+
+fn scalar_kind(ty: &str) -> Option<ScalarKind> {
+    match ty {
+        "bool" => Some(ScalarKind::Bool),
+        "char" => Some(ScalarKind::Char),
+        _ => None,
+    }
+}
+
+impl ScalarKind {
+    /// Converts `u32` received from the other side, evaluates to `Result<T, RuntimeError>`.
+    fn decode(self, value: &Ident) -> Expr {
+        match self {
+            ScalarKind::Bool => parse_quote!(Ok::<_, scotch_host::RuntimeError>(#value != 0)),
+            ScalarKind::Char => parse_quote! {
+                char::from_u32(#value)
+                    .ok_or_else(|| scotch_host::RuntimeError::new("Value is not a valid char"))
+            },
+        }
+    }
+}
+
+fn is_wide_type(ty: &str) -> bool {
+    ty == "u128" || ty == "i128"
+}
+
+fn guest_pointer_sized(ty: &str) -> Option<Type> {
+    match ty {
+        "usize" => Some(parse_quote!(scotch_host::GuestUsize)),
+        "isize" => Some(parse_quote!(scotch_host::GuestIsize)),
+        _ => None,
+    }
+}
+
+/// Names of the pointer, value and length used to pass `&mut T`.
+fn mut_idents(name: &Ident) -> (Ident, Ident, Ident) {
+    (
+        format_ident!("__{name}_ptr"),
+        format_ident!("__{name}_value"),
+        format_ident!("__{name}_len"),
+    )
+}
+
+/// Names of the halves `u128` and `i128` are split into.
+fn split_idents(name: &Ident) -> (Ident, Ident) {
+    (format_ident!("__{name}_lo"), format_ident!("__{name}_hi"))
+}
+
+#[derive(Clone, Copy)]
+enum BytesKind {
+    Bytes,
+    Str,
+}
+
+/// Detects `&[u8]` and `&str` parameters, or `Vec<u8>` and `String` return values,
+/// that are copied as raw bytes without encoding.
+fn bytes_kind(ty: &Type, allow_owned: bool) -> Option<BytesKind> {
+    fn last_segment(ty: &Type) -> Option<&syn::PathSegment> {
+        match ty {
+            Type::Path(path) if path.qself.is_none() => path.path.segments.last(),
+            _ => None,
+        }
+    }
+
+    fn is_u8(ty: &Type) -> bool {
+        last_segment(ty).is_some_and(|seg| seg.ident == "u8")
+    }
+
+    if allow_owned {
+        let seg = last_segment(ty)?;
+        if seg.ident == "String" {
+            return Some(BytesKind::Str);
+        }
+
+        let PathArguments::AngleBracketed(args) = &seg.arguments else {
+            return None;
+        };
+        return match args.args.first() {
+            Some(GenericArgument::Type(elem)) if seg.ident == "Vec" && is_u8(elem) => {
+                Some(BytesKind::Bytes)
+            }
+            _ => None,
+        };
+    }
+
+    match ty {
+        Type::Reference(TypeReference {
+            lifetime: None,
+            mutability: None,
+            elem,
+            ..
+        }) => match elem.as_ref() {
+            Type::Slice(slice) if is_u8(&slice.elem) => Some(BytesKind::Bytes),
+            elem if last_segment(elem).is_some_and(|seg| seg.ident == "str") => {
+                Some(BytesKind::Str)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Names of the offset and length raw bytes are passed as.
+fn bytes_idents(name: &Ident) -> (Ident, Ident) {
+    (format_ident!("__{name}_ptr"), format_ident!("__{name}_len"))
 }
 
 #[derive(Clone, Copy)]
@@ -24,32 +138,93 @@ enum WrapMode {
 }
 
 impl WrapMode {
-    fn wrap(self, ty: Type) -> Type {
+    fn wrap(self, ty: Type, codec: &Path) -> Type {
+        match self {
+            WrapMode::Encoded => {
+                parse_quote!(scotch_host::EncodedPtr<#ty, scotch_host::GuestMemory, #codec>)
+            }
+            WrapMode::Managed => {
+                parse_quote!(scotch_host::ManagedPtr<#ty, scotch_host::GuestMemory, #codec>)
+            }
+        }
+    }
+
+    fn wrap_mut(self, ty: Type, codec: &Path) -> Type {
         match self {
-            WrapMode::Encoded => parse_quote!(scotch_host::EncodedPtr<#ty>),
-            WrapMode::Managed => parse_quote!(scotch_host::ManagedPtr<#ty>),
+            WrapMode::Encoded => {
+                parse_quote!(scotch_host::EncodedMutPtr<#ty, scotch_host::GuestMemory, #codec>)
+            }
+            WrapMode::Managed => {
+                parse_quote!(scotch_host::ManagedMutPtr<#ty, scotch_host::GuestMemory, #codec>)
+            }
         }
     }
 }
 
+/// Codec used when the macro was not given `codec = path`.
+fn default_codec() -> Path {
+    parse_quote!(scotch_host::Bincode)
+}
+
+/// Parses `codec = path` argument of the macros.
+fn parse_codec(input: ParseStream) -> syn::Result<Path> {
+    let name: Ident = input.parse()?;
+    if name != "codec" {
+        return Err(syn::Error::new_spanned(name, "Expected `codec = path`"));
+    }
+    let _: Token![=] = input.parse()?;
+    input.parse()
+}
+
 enum TypeTranslation {
     Original,
+    /// `usize` or `isize` that has to be converted to the guest pointer width.
+    PointerSized(Type),
+    /// `u128` or `i128` passed as two `u64`.
+    Split,
+    /// `bool` or `char` passed as `u32`.
+    Scalar(ScalarKind),
     Wrapped(Type),
+    /// `&mut T` that is written back after the call.
+    WrappedMut(Type),
+    /// `&[u8]`, `&str`, `Vec<u8>` or `String` copied without encoding.
+    Raw(BytesKind),
 }
 
-fn translate_type(ty: Type, mode: WrapMode, allow_owned: bool) -> TypeTranslation {
+fn translate_type(ty: Type, mode: WrapMode, codec: &Path, allow_owned: bool) -> TypeTranslation {
+    let name = match &ty {
+        Type::Path(path) => path.path.segments.last().unwrap().ident.to_string(),
+        _ => String::new(),
+    };
+
+    if let Some(kind) = bytes_kind(&ty, allow_owned) {
+        return TypeTranslation::Raw(kind);
+    }
+
     match ty {
-        Type::Path(path) if is_atom_type(&path.path.segments.last().unwrap().ident.to_string()) => {
-            TypeTranslation::Original
+        Type::Path(_) if is_atom_type(&name) => TypeTranslation::Original,
+        Type::Path(_) if scalar_kind(&name).is_some() => {
+            TypeTranslation::Scalar(scalar_kind(&name).unwrap())
         }
+        Type::Path(_) if guest_pointer_sized(&name).is_some() => {
+            TypeTranslation::PointerSized(guest_pointer_sized(&name).unwrap())
+        }
+        // Multi-value returns are not available so wide integers are encoded when returned.
+        Type::Path(_) if is_wide_type(&name) && !allow_owned => TypeTranslation::Split,
         Type::Reference(TypeReference {
             lifetime: None,
             mutability: None,
             elem,
             ..
-        }) => TypeTranslation::Wrapped(mode.wrap(*elem)),
-        Type::Array(_) | Type::Tuple(_) => TypeTranslation::Wrapped(mode.wrap(ty)),
-        Type::Path(_) if allow_owned => TypeTranslation::Wrapped(mode.wrap(ty)),
+        }) => TypeTranslation::Wrapped(mode.wrap(*elem, codec)),
+        Type::Reference(TypeReference {
+            lifetime: None,
+            mutability: Some(_),
+            elem,
+            ..
+        }) if !allow_owned => TypeTranslation::WrappedMut(mode.wrap_mut(*elem, codec)),
+        Type::Array(_) | Type::Tuple(_) => TypeTranslation::Wrapped(mode.wrap(ty, codec)),
+        Type::Path(_) if allow_owned => TypeTranslation::Wrapped(mode.wrap(ty, codec)),
         _ => unimplemented!("Type is unsupported, consider using a reference instead."),
     }
 }
@@ -57,53 +232,198 @@ fn translate_type(ty: Type, mode: WrapMode, allow_owned: bool) -> TypeTranslatio
 #[derive(Default)]
 struct HostInputTranslation {
     prelude: Vec<Stmt>,
+    post: Vec<Stmt>,
 }
 
-fn translate_host_inputs<'a>(it: impl Iterator<Item = &'a mut FnArg>) -> HostInputTranslation {
+fn translate_host_inputs(
+    inputs: &mut Punctuated<FnArg, Token![,]>,
+    codec: &Path,
+) -> HostInputTranslation {
     let mut out = HostInputTranslation::default();
 
-    it.map(|arg| {
-        if let FnArg::Typed(arg) = arg {
-            arg
-        } else {
-            panic!("self is not allowed in host functions")
-        }
-    })
-    .map(|arg| {
-        if let Pat::Ident(id) = arg.pat.as_mut() {
-            (&id.ident, &mut arg.ty)
-        } else {
-            panic!("Invalid host function argument name")
-        }
-    })
-    .for_each(|(name, ty)| {
-        if let TypeTranslation::Wrapped(new) =
-            translate_type(ty.as_ref().clone(), WrapMode::Managed, false)
-        {
-            out.prelude
-                .push(parse_quote!(let #name: #ty = &#name.read(&__view).unwrap().0;));
-            *ty.as_mut() = new;
-        }
-    });
+    *inputs = std::mem::take(inputs)
+        .into_iter()
+        .map(|arg| {
+            if let FnArg::Typed(arg) = arg {
+                arg
+            } else {
+                panic!("self is not allowed in host functions")
+            }
+        })
+        .flat_map(|mut arg| {
+            let name = if let Pat::Ident(id) = arg.pat.as_ref() {
+                id.ident.clone()
+            } else {
+                panic!("Invalid host function argument name")
+            };
+            let ty = arg.ty.as_ref().clone();
+
+            match translate_type(ty.clone(), WrapMode::Managed, codec, false) {
+                TypeTranslation::Original => vec![FnArg::Typed(arg)],
+                TypeTranslation::PointerSized(new) => {
+                    out.prelude.push(parse_quote!(let #name = #name as #ty;));
+                    *arg.ty = new;
+                    vec![FnArg::Typed(arg)]
+                }
+                TypeTranslation::Scalar(kind) => {
+                    let convert = kind.decode(&name);
+                    out.prelude.push(parse_quote!(let #name: #ty = #convert?;));
+                    *arg.ty = parse_quote!(u32);
+                    vec![FnArg::Typed(arg)]
+                }
+                TypeTranslation::Split => {
+                    let (lo, hi) = split_idents(&name);
+                    out.prelude.push(
+                        parse_quote!(let #name = (((#hi as u128) << 64) | #lo as u128) as #ty;),
+                    );
+                    vec![parse_quote!(#lo: u64), parse_quote!(#hi: u64)]
+                }
+                TypeTranslation::Wrapped(new) => {
+                    out.prelude
+                        .push(parse_quote!(let #name: #ty = &#name.read(&__view)?.0;));
+                    *arg.ty = new;
+                    vec![FnArg::Typed(arg)]
+                }
+                TypeTranslation::WrappedMut(new) => {
+                    let (ptr, value, len) = mut_idents(&name);
+                    out.prelude.extend::<[Stmt; 3]>([
+                        parse_quote!(let #ptr = #name;),
+                        parse_quote!(let (mut #value, #len) = #ptr.read(&__view)?;),
+                        parse_quote!(let #name: #ty = &mut #value;),
+                    ]);
+                    out.post.push(
+                        parse_quote!(#ptr.write_back_in(&#value, #len, &mut __env, &*__instance)?;),
+                    );
+                    *arg.ty = new;
+                    vec![FnArg::Typed(arg)]
+                }
+                TypeTranslation::Raw(kind) => {
+                    let (ptr, len) = bytes_idents(&name);
+                    out.prelude.push(match kind {
+                        BytesKind::Bytes => parse_quote! {
+                            let #name: #ty = &scotch_host::read_bytes(&__view, #ptr, #len)?;
+                        },
+                        BytesKind::Str => parse_quote! {
+                            let #name: #ty = &scotch_host::read_string(&__view, #ptr, #len)?;
+                        },
+                    });
+                    vec![
+                        parse_quote!(#ptr: scotch_host::GuestUsize),
+                        parse_quote!(#len: scotch_host::GuestUsize),
+                    ]
+                }
+            }
+        })
+        .collect();
 
     out
 }
 
-fn translate_host_output(ret: &mut ReturnType) -> Stmt {
-    let mut out = parse_quote!(return out;);
+fn result_ok_type(ty: &Type) -> Option<Type> {
+    let Type::Path(path) = ty else { return None };
+    let last = path.path.segments.last()?;
+    if last.ident != "Result" {
+        return None;
+    }
+
+    let PathArguments::AngleBracketed(args) = &last.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(ok) => Some(ok.clone()),
+        _ => None,
+    }
+}
+
+fn translate_host_output(ret: &mut ReturnType, trap: bool, codec: &Path) -> Stmt {
+    let mut out = parse_quote!(return Ok(out););
 
     if let ReturnType::Type(_, ty) = ret {
-        if let TypeTranslation::Wrapped(new) =
-            translate_type(ty.as_ref().clone(), WrapMode::Managed, true)
-        {
-            *ty = Box::new(new);
-            out = parse_quote!(return scotch_host::EncodedPtr::new_in(&out, &mut __env, &*__instance).unwrap().to_managed(););
+        if trap {
+            **ty =
+                result_ok_type(ty).expect("Host functions with `trap` must return `Result<T, E>`");
+        }
+
+        match translate_type(ty.as_ref().clone(), WrapMode::Managed, codec, true) {
+            TypeTranslation::Wrapped(new) => {
+                **ty = new;
+                out = parse_quote! {
+                    return Ok(scotch_host::EncodedPtr::<_, scotch_host::GuestMemory, #codec>::new_in(
+                        &out,
+                        &mut __env,
+                        &*__instance,
+                    )?
+                    .to_managed());
+                };
+            }
+            TypeTranslation::Scalar(_) => {
+                out = parse_quote!(return Ok(out as u32););
+                **ty = parse_quote!(u32);
+            }
+            TypeTranslation::PointerSized(new) => {
+                out = parse_quote! {
+                    return <#new>::try_from(out).map_err(|_| {
+                        scotch_host::RuntimeError::new("Value does not fit into guest pointer width")
+                    });
+                };
+                **ty = new;
+            }
+            TypeTranslation::Raw(_) => {
+                **ty = parse_quote!(scotch_host::ManagedBytes);
+                out = parse_quote! {
+                    return Ok(scotch_host::EncodedBytes::new_in(
+                        AsRef::<[u8]>::as_ref(&out),
+                        &mut __env,
+                        &*__instance,
+                    )?
+                    .to_managed());
+                };
+            }
+            TypeTranslation::Original | TypeTranslation::Split => (),
+            TypeTranslation::WrappedMut(_) => unreachable!(),
         }
     }
 
     out
 }
 
+#[derive(Default)]
+struct HostFunctionArgs {
+    state: Option<Path>,
+    trap: bool,
+    codec: Option<Path>,
+}
+
+impl Parse for HostFunctionArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut out = Self::default();
+
+        while !input.is_empty() {
+            if input.peek(Ident) && input.peek2(Token![=]) {
+                out.codec = Some(parse_codec(input)?);
+            } else {
+                let path: Path = input.parse()?;
+                if path.is_ident("trap") {
+                    out.trap = true;
+                } else if out.state.is_none() {
+                    out.state = Some(path);
+                } else {
+                    return Err(syn::Error::new_spanned(
+                        path,
+                        "Expected state type, optional `trap` and `codec = path`",
+                    ));
+                }
+            }
+
+            if !input.is_empty() {
+                let _: Token![,] = input.parse()?;
+            }
+        }
+
+        Ok(out)
+    }
+}
+
 /// Macro used to annotate host function that can be exposed to guest.
 /// ```ignore
 /// #[host_function]
@@ -111,20 +431,39 @@ fn translate_host_output(ret: &mut ReturnType) -> Stmt {
 ///     println!("Wasm: {text}");
 /// }
 /// ```
+/// Host function can return `Result<T, E>`, by default it is encoded and delivered
+/// to the guest which should declare the function as returning `Result<T, E>` as well.
+/// With `trap` the error is converted to a wasm trap instead
+/// and the guest sees the function as returning `T`.
+/// ```ignore
+/// #[host_function(MyState, trap)]
+/// fn load(name: &String) -> Result<String, std::io::Error> {
+///     std::fs::read_to_string(name)
+/// }
+/// ```
+/// Arguments that guest failed to pass correctly also result in a trap.
+/// Values are encoded with `scotch_host::Bincode` unless another codec is given,
+/// e.g. `#[host_function(MyState, codec = scotch_host::Postcard)]`.
+///
+/// Host function can be `async`, the future is driven to completion on the thread
+/// that runs the guest, use `WasmPlugin::call_async` so that it is not an executor thread.
+/// ```ignore
+/// #[host_function]
+/// async fn fetch(url: &String) -> String {
+///     client.get(url).send().await.unwrap().text().await.unwrap()
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn host_function(args: TokenStream, input: TokenStream) -> TokenStream {
-    let env_type = if args.is_empty() {
-        quote!(scotch_host::FunctionEnvMut<scotch_host::WasmEnv<()>>)
-    } else {
-        let path = parse_macro_input!(args as Path);
+    let HostFunctionArgs { state, trap, codec } = parse_macro_input!(args as HostFunctionArgs);
+    let codec = codec.unwrap_or_else(default_codec);
+    let env_type = if let Some(path) = state {
         quote!(scotch_host::FunctionEnvMut<scotch_host::WasmEnv<#path>>)
+    } else {
+        quote!(scotch_host::FunctionEnvMut<scotch_host::WasmEnv<()>>)
     };
 
     let mut item_fn = parse_macro_input!(input as ItemFn);
-    assert!(
-        item_fn.sig.asyncness.is_none(),
-        "Host function can not be async"
-    );
     assert!(
         item_fn.sig.constness.is_none(),
         "Host function can not be const"
@@ -133,25 +472,58 @@ pub fn host_function(args: TokenStream, input: TokenStream) -> TokenStream {
     let ident = &item_fn.sig.ident;
     let vis = &item_fn.vis;
 
-    let HostInputTranslation { prelude } = translate_host_inputs(item_fn.sig.inputs.iter_mut());
+    let HostInputTranslation { prelude, post } =
+        translate_host_inputs(&mut item_fn.sig.inputs, &codec);
 
     let args = &item_fn.sig.inputs;
 
     let original_output = item_fn.sig.output.clone();
     let block = &item_fn.block;
 
-    let epilogue = translate_host_output(&mut item_fn.sig.output);
-    let output = &item_fn.sig.output;
+    let epilogue = translate_host_output(&mut item_fn.sig.output, trap, &codec);
+    let output: Type = match &item_fn.sig.output {
+        ReturnType::Type(_, ty) => parse_quote!(Result<#ty, scotch_host::RuntimeError>),
+        ReturnType::Default => parse_quote!(Result<(), scotch_host::RuntimeError>),
+    };
 
-    let out = quote! {
-        #vis fn #ident(mut __env: #env_type, #args) #output {
-            let __instance = __env.data().instance.upgrade().unwrap();
-            let __view = __instance.exports.get_memory("memory").expect("Memory is missing").view(&__env);
+    let body: Expr = if item_fn.sig.asyncness.is_some() {
+        let ret: Type = match &original_output {
+            ReturnType::Type(_, ty) => ty.as_ref().clone(),
+            ReturnType::Default => parse_quote!(()),
+        };
+        parse_quote!(scotch_host::block_on::<#ret, _>(async move #block))
+    } else {
+        parse_quote!((move || #original_output #block)())
+    };
 
-            let state = &mut __env.data_mut().state;
+    let call: Stmt = if trap {
+        parse_quote! {
+            let out = #body
+                .map_err(|e| scotch_host::RuntimeError::new(e.to_string()))?;
+        }
+    } else {
+        parse_quote!(let out = #body;)
+    };
+
+    let out = quote! {
+        #vis fn #ident(mut __env: #env_type, #args) -> #output {
+            __env.data().running.check()?;
+            let __instance = __env
+                .data()
+                .instance
+                .upgrade()
+                .ok_or_else(|| scotch_host::RuntimeError::new("Plugin instance is not available"))?;
+            let __view = __instance
+                .exports
+                .get_memory("memory")
+                .map_err(scotch_host::ScotchHostError::MemoryMissing)?
+                .view(&__env);
+
+            let state = __env.data_mut().state_mut();
 
             #(#prelude)*
-            let out = (move || #original_output #block)();
+            #call
+            #(#post)*
             #epilogue
         }
     };
@@ -199,11 +571,15 @@ struct HandleGenerationData {
     callback_types: Vec<Type>,
     callback_args: Vec<BareFnArg>,
     dispatch_types: Vec<Type>,
+    dispatch_args: Vec<Ident>,
     pre_dispatch: Vec<Stmt>,
     post_dispatch: Vec<Stmt>,
 }
 
-fn prepare_handle_gen_data(args: impl Iterator<Item = PatType>) -> HandleGenerationData {
+fn prepare_handle_gen_data(
+    args: impl Iterator<Item = PatType>,
+    codec: &Path,
+) -> HandleGenerationData {
     let mut out = HandleGenerationData::default();
 
     args.into_iter().for_each(|arg| {
@@ -216,52 +592,144 @@ fn prepare_handle_gen_data(args: impl Iterator<Item = PatType>) -> HandleGenerat
         let ty = &arg.ty;
         out.callback_args.push(parse_quote!(#name: #ty));
 
-        match translate_type(arg.ty.as_ref().clone(), WrapMode::Encoded, false) {
+        match translate_type(arg.ty.as_ref().clone(), WrapMode::Encoded, codec, false) {
             TypeTranslation::Wrapped(new) => {
                 let pre = parse_quote! {
-                    let #name: #new = scotch_host::EncodedPtr::new_in(#name, &mut *store.write(), &*instance).expect("Alloc failed");
+                    let #name: #new = scotch_host::EncodedPtr::new_in(#name, call.store(), &*instance)?;
                 };
                 let post = parse_quote! {
-                    #name.free_in(&mut *store.write(), &*instance).expect("Free failed");
+                    #name.free_in(call.store(), &*instance)?;
                 };
 
                 out.pre_dispatch.push(pre);
                 out.post_dispatch.push(post);
                 out.dispatch_types.push(new);
+                out.dispatch_args.push(name);
+            }
+            TypeTranslation::PointerSized(new) => {
+                out.pre_dispatch.push(parse_quote! {
+                    let #name = match <#new>::try_from(#name) {
+                        Ok(v) => v,
+                        Err(_) => return Err(scotch_host::RuntimeError::new(
+                            "Value does not fit into guest pointer width",
+                        )),
+                    };
+                });
+                out.dispatch_types.push(new);
+                out.dispatch_args.push(name);
+            }
+            TypeTranslation::WrappedMut(new) => {
+                let (ptr, ..) = mut_idents(&name);
+                out.pre_dispatch.push(parse_quote! {
+                    let #ptr: #new = scotch_host::EncodedMutPtr::new_in(&*#name, call.store(), &*instance)?;
+                });
+                out.post_dispatch.push(parse_quote! {
+                    match #ptr.read_back_in(call.store(), &*instance) {
+                        Ok(value) => *#name = value,
+                        Err(e) => return Err(scotch_host::RuntimeError::from(e)),
+                    }
+                });
+                out.dispatch_types.push(new);
+                out.dispatch_args.push(ptr);
+            }
+            TypeTranslation::Scalar(_) => {
+                out.pre_dispatch.push(parse_quote!(let #name = #name as u32;));
+                out.dispatch_types.push(parse_quote!(u32));
+                out.dispatch_args.push(name);
+            }
+            TypeTranslation::Split => {
+                let (lo, hi) = split_idents(&name);
+                out.pre_dispatch.push(
+                    parse_quote!(let (#lo, #hi) = (#name as u128 as u64, ((#name as u128) >> 64) as u64);),
+                );
+                out.dispatch_types.push(parse_quote!(u64));
+                out.dispatch_types.push(parse_quote!(u64));
+                out.dispatch_args.push(lo);
+                out.dispatch_args.push(hi);
+            }
+            TypeTranslation::Raw(_) => {
+                let (ptr, len) = bytes_idents(&name);
+                out.pre_dispatch.push(parse_quote! {
+                    let #name = scotch_host::EncodedBytes::<scotch_host::GuestMemory>::new_in(AsRef::<[u8]>::as_ref(#name), call.store(), &*instance)?;
+                });
+                out.pre_dispatch
+                    .push(parse_quote!(let (#ptr, #len) = #name.parts()?;));
+                out.post_dispatch.push(parse_quote! {
+                    #name.free_in(call.store(), &*instance)?;
+                });
+                out.dispatch_types
+                    .push(parse_quote!(scotch_host::GuestUsize));
+                out.dispatch_types
+                    .push(parse_quote!(scotch_host::GuestUsize));
+                out.dispatch_args.push(ptr);
+                out.dispatch_args.push(len);
+            }
+            TypeTranslation::Original => {
+                out.dispatch_types.push(*arg.ty);
+                out.dispatch_args.push(name);
             }
-            TypeTranslation::Original => out.dispatch_types.push(*arg.ty),
         }
     });
 
     out
 }
 
-fn handle_from_function(mut func: ForeignItemFn) -> TokenStream2 {
+fn handle_from_function(mut func: ForeignItemFn, codec: &Path) -> TokenStream2 {
     let mut ending: Stmt = parse_quote!(return out;);
     let (callback_return_type, dispatch_return_type): (Type, Type) =
         if let ReturnType::Type(_, ref mut ty) = func.sig.output {
-            let out_ty = if let TypeTranslation::Wrapped(new) =
-                translate_type(ty.as_ref().clone(), WrapMode::Managed, true)
-            {
-                ending = parse_quote! {
-                    return out.map(|ptr| {
-                        let out = ptr.read(
-                            &instance.exports
-                                .get_memory("memory")
-                                .expect("Memory is missing")
-                                .view(&*store.read())
-                        ).map_err(|e| scotch_host::RuntimeError::new(e.to_string()));
-                        if let Ok((_, len)) = out {
-                            // TODO: Should be handled somehow?
-                            _ = ptr.free_in(len, &mut *store.write(), &*instance);
-                        }
-
-                        out.map(|(val, _)| val)
-                    }).and_then(|x| x);
-                };
-                new
-            } else {
-                ty.as_ref().clone()
+            let out_ty = match translate_type(ty.as_ref().clone(), WrapMode::Managed, codec, true) {
+                TypeTranslation::Wrapped(new) => {
+                    ending = parse_quote! {
+                        return out.map(|ptr| {
+                            let out = ptr.read(
+                                &instance.exports
+                                    .get_memory("memory")
+                                    .expect("Memory is missing")
+                                    .view(&*call.store())
+                            ).map_err(|e| scotch_host::RuntimeError::new(e.to_string()));
+                            if let Ok((_, len)) = out {
+                                // TODO: Should be handled somehow?
+                                _ = ptr.free_in(len, call.store(), &*instance);
+                            }
+
+                            out.map(|(val, _)| val)
+                        }).and_then(|x| x);
+                    };
+                    new
+                }
+                TypeTranslation::PointerSized(new) => {
+                    ending = parse_quote!(return out.map(|out| out as #ty););
+                    new
+                }
+                TypeTranslation::Scalar(kind) => {
+                    let out = format_ident!("out");
+                    let convert = kind.decode(&out);
+                    ending = parse_quote!(return out.and_then(|out| #convert););
+                    parse_quote!(u32)
+                }
+                TypeTranslation::Raw(kind) => {
+                    let read = match kind {
+                        BytesKind::Bytes => format_ident!("read_bytes"),
+                        BytesKind::Str => format_ident!("read_string"),
+                    };
+                    ending = parse_quote! {
+                        return out.and_then(|bytes| {
+                            let out = bytes.#read(
+                                &instance.exports
+                                    .get_memory("memory")
+                                    .expect("Memory is missing")
+                                    .view(&*call.store())
+                            )?;
+                            bytes.free_in(out.len(), call.store(), &*instance)?;
+
+                            Ok(out)
+                        });
+                    };
+                    parse_quote!(scotch_host::ManagedBytes)
+                }
+                TypeTranslation::Original | TypeTranslation::Split => ty.as_ref().clone(),
+                TypeTranslation::WrappedMut(_) => unreachable!(),
             };
 
             (parse_quote!(Result<#ty, scotch_host::RuntimeError>), out_ty)
@@ -314,13 +782,17 @@ fn handle_from_function(mut func: ForeignItemFn) -> TokenStream2 {
         pre_dispatch,
         post_dispatch,
         dispatch_types,
-    } = prepare_handle_gen_data(func.sig.inputs.clone().into_iter().map(|arg| {
-        if let FnArg::Typed(arg) = arg {
-            arg
-        } else {
-            panic!("self is not supported in guest functions.")
-        }
-    }));
+        dispatch_args,
+    } = prepare_handle_gen_data(
+        func.sig.inputs.clone().into_iter().map(|arg| {
+            if let FnArg::Typed(arg) = arg {
+                arg
+            } else {
+                panic!("self is not supported in guest functions.")
+            }
+        }),
+        codec,
+    );
 
     let dispatch_types = if dispatch_types.len() == 1 {
         quote!(#(#dispatch_types)*)
@@ -328,18 +800,11 @@ fn handle_from_function(mut func: ForeignItemFn) -> TokenStream2 {
         quote!((#(#dispatch_types),*))
     };
 
-    let arg_names = callback_args.iter().enumerate().map(|(i, arg)| {
-        arg.name
-            .clone()
-            .map(|(i, _)| i)
-            .unwrap_or_else(|| format_ident!("arg{i}"))
-    });
-
     quote! {
         #[allow(non_camel_case_types)]
         #vis struct #handle_ident;
         unsafe impl scotch_host::GuestFunctionHandle for #handle_ident {
-            type Callback = Box<dyn Fn(#(#callback_types),*) -> #callback_return_type>;
+            type Callback = Box<dyn Fn(#(#callback_types),*) -> #callback_return_type + Send + Sync>;
         }
 
         unsafe impl scotch_host::GuestFunctionCreator for #handle_ident {
@@ -354,21 +819,28 @@ fn handle_from_function(mut func: ForeignItemFn) -> TokenStream2 {
                 instance: scotch_host::InstanceRef,
             ) -> Option<(std::any::TypeId, scotch_host::CallbackRef)> {
                 let typed_fn: scotch_host::TypedFunction<#dispatch_types, #dispatch_return_type> = instance.exports
-                    .get_typed_function(&*store.read(), stringify!(#export_ident))
-                    .unwrap();
+                    .get_typed_function(&*store.lock(), stringify!(#export_ident))
+                    .ok()?;
 
                 let callback = Box::new(move |#(#callback_args),*| {
-                    #(#pre_dispatch)*
-                    let out = typed_fn.call(&mut *store.write(), #(#arg_names),*);
-                    #(#post_dispatch)*
+                    store.call(&instance, |call| {
+                        #(#pre_dispatch)*
+                        let out = typed_fn.call(call.store(), #(#dispatch_args),*);
+                        #(#post_dispatch)*
 
-                    #ending
+                        #ending
+                    })
                 }) as <Self as scotch_host::GuestFunctionHandle>::Callback;
 
-                let any = Box::new(callback) as Box<dyn core::any::Any>;
+                let any = Box::new(callback) as scotch_host::CallbackRef;
 
                 Some((std::any::TypeId::of::<#handle_ident>(), any))
             }
+
+            #[inline(always)]
+            fn codec(&self) -> scotch_host::CodecId {
+                <#codec as scotch_host::Codec>::ID
+            }
         }
     }
 }
@@ -379,14 +851,29 @@ fn handle_from_function(mut func: ForeignItemFn) -> TokenStream2 {
 ///     pub add_up_list: fn(nums: &Vec<i32>) -> i32;
 /// }
 /// ```
+/// Guest functions returning `Result<T, E>` produce `Result<Result<T, E>, RuntimeError>`,
+/// use `GuestResultExt::flatten_guest` to combine both errors into `GuestError<E>`.
+///
+/// Values are encoded with `scotch_host::Bincode` unless another codec is given.
+/// ```ignore
+/// #[guest_functions(codec = scotch_host::Postcard)]
+/// extern "C" {
+///     pub fn add_up_list(nums: &Vec<i32>) -> i32;
+/// }
+/// ```
 #[proc_macro_attribute]
-pub fn guest_functions(_: TokenStream, input: TokenStream) -> TokenStream {
+pub fn guest_functions(args: TokenStream, input: TokenStream) -> TokenStream {
+    let codec = if args.is_empty() {
+        default_codec()
+    } else {
+        parse_macro_input!(args with parse_codec)
+    };
     let handles = parse_macro_input!(input as ItemForeignMod)
         .items
         .into_iter()
         .map(|item| {
             if let ForeignItem::Fn(func) = item {
-                handle_from_function(func)
+                handle_from_function(func, &codec)
             } else {
                 panic!("Only functions are supported")
             }
@@ -2,20 +2,21 @@ use criterion::{criterion_group, criterion_main, Criterion};
 use scotch_host::{guest_functions, make_exports, WasmPlugin};
 use std::{hint::black_box, iter::successors};
 
-guest_functions! {
-    pub add_up_list: fn(list: &Vec<i32>) -> i32;
+#[guest_functions]
+extern "C" {
+    pub fn add_up_list(list: &Vec<i32>) -> i32;
 }
 
 fn call(plugin: &WasmPlugin, numbers: &Vec<i32>, result: i32) {
     assert_eq!(
-        black_box(plugin.function::<add_up_list>()(numbers)).unwrap(),
+        black_box(plugin.function_unwrap::<add_up_list>()(numbers)).unwrap(),
         result
     );
 }
 
 fn bench_call(c: &mut Criterion) {
     let plugin = WasmPlugin::builder()
-        .with_env(())
+        .with_state(())
         .from_binary(include_bytes!("../../examples/runner/plugin.wasm"))
         .unwrap()
         .with_exports(make_exports!(add_up_list))
@@ -0,0 +1,157 @@
+use crate::{
+    header::{Header, HEADER_SIZE},
+    memory::{alloc_in, free_in},
+    GuestMemory, ScotchHostError,
+};
+use bincode::error::DecodeError;
+use std::marker::PhantomData;
+use wasmer::{
+    AsStoreMut, FromToNativeWasmType, Instance, MemoryAccessError, MemorySize, MemoryView,
+    NativeWasmTypeInto,
+};
+
+/// Raw bytes copied to the guest memory without encoding.
+#[doc(hidden)]
+pub struct EncodedBytes<M: MemorySize = GuestMemory> {
+    offset: M::Offset,
+    size: usize,
+}
+
+impl<M: MemorySize> EncodedBytes<M> {
+    pub fn new_in(
+        bytes: &[u8],
+        store: &mut impl AsStoreMut,
+        instance: &Instance,
+    ) -> Result<Self, ScotchHostError> {
+        let ptr = alloc_in(bytes.len() + HEADER_SIZE, store, instance)?;
+        let view = instance
+            .exports
+            .get_memory("memory")
+            .map_err(ScotchHostError::MemoryMissing)?
+            .view(store);
+
+        view.write(ptr, &Header::new(bytes.len()).to_bytes())?;
+        view.write(ptr + HEADER_SIZE as u64, bytes)?;
+
+        Ok(Self {
+            offset: ptr.try_into().map_err(|_| MemoryAccessError::Overflow)?,
+            size: bytes.len(),
+        })
+    }
+
+    /// Offset and length of the bytes, used to pass them as an argument.
+    pub fn parts(&self) -> Result<(M::Offset, M::Offset), ScotchHostError> {
+        let offset: u64 = self.offset.into();
+        let offset = offset
+            .checked_add(HEADER_SIZE as u64)
+            .and_then(|offset| M::Offset::try_from(offset).ok());
+        let size = M::Offset::try_from(self.size as u64).ok();
+
+        match (offset, size) {
+            (Some(offset), Some(size)) => Ok((offset, size)),
+            _ => Err(MemoryAccessError::Overflow.into()),
+        }
+    }
+
+    pub fn to_managed(&self) -> ManagedBytes<M> {
+        ManagedBytes {
+            offset: self.offset,
+            _m: PhantomData,
+        }
+    }
+
+    pub fn free_in(
+        self,
+        store: &mut impl AsStoreMut,
+        instance: &Instance,
+    ) -> Result<(), ScotchHostError> {
+        free_in(self.offset.into(), self.size + HEADER_SIZE, store, instance)
+    }
+}
+
+/// Raw bytes created by the guest.
+#[doc(hidden)]
+pub struct ManagedBytes<M: MemorySize = GuestMemory> {
+    offset: M::Offset,
+    _m: PhantomData<M>,
+}
+
+impl<M: MemorySize> ManagedBytes<M> {
+    pub fn read_bytes(&self, view: &MemoryView) -> Result<Vec<u8>, ScotchHostError> {
+        let offset: u64 = self.offset.into();
+        let len = Header::read(view, offset)?.len;
+
+        read_bytes(view, offset + HEADER_SIZE as u64, len)
+    }
+
+    pub fn read_string(&self, view: &MemoryView) -> Result<String, ScotchHostError> {
+        to_string(self.read_bytes(view)?)
+    }
+
+    /// `len` is the length of the data that was read.
+    pub fn free_in(
+        &self,
+        len: usize,
+        store: &mut impl AsStoreMut,
+        instance: &Instance,
+    ) -> Result<(), ScotchHostError> {
+        free_in(self.offset.into(), len + HEADER_SIZE, store, instance)
+    }
+}
+
+unsafe impl<M: MemorySize> FromToNativeWasmType for ManagedBytes<M>
+where
+    M::Native: NativeWasmTypeInto,
+{
+    type Native = M::Native;
+
+    #[inline]
+    fn from_native(native: Self::Native) -> Self {
+        Self {
+            offset: M::native_to_offset(native),
+            _m: PhantomData,
+        }
+    }
+
+    #[inline]
+    fn to_native(self) -> Self::Native {
+        M::offset_to_native(self.offset)
+    }
+}
+
+/// Copies `len` bytes at `offset` from the guest memory.
+#[doc(hidden)]
+pub fn read_bytes(
+    view: &MemoryView,
+    offset: impl Into<u64>,
+    len: impl Into<u64>,
+) -> Result<Vec<u8>, ScotchHostError> {
+    let (offset, len) = (offset.into(), len.into());
+    match offset.checked_add(len) {
+        Some(end) if end <= view.data_size() => (),
+        _ => return Err(MemoryAccessError::HeapOutOfBounds.into()),
+    }
+
+    let mut buf = vec![0; len as usize];
+    view.read(offset, &mut buf[..])?;
+    Ok(buf)
+}
+
+/// Copies `len` bytes at `offset` from the guest memory and validates them as UTF-8.
+#[doc(hidden)]
+pub fn read_string(
+    view: &MemoryView,
+    offset: impl Into<u64>,
+    len: impl Into<u64>,
+) -> Result<String, ScotchHostError> {
+    to_string(read_bytes(view, offset, len)?)
+}
+
+fn to_string(bytes: Vec<u8>) -> Result<String, ScotchHostError> {
+    String::from_utf8(bytes).map_err(|e| {
+        DecodeError::Utf8 {
+            inner: e.utf8_error(),
+        }
+        .into()
+    })
+}
@@ -0,0 +1,108 @@
+use crate::compiler::EngineConfig;
+use sha2::{Digest, Sha256};
+use std::{
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use wasmer::{Module, Store, Target};
+
+const EXTENSION: &str = "wasmu";
+
+/// Directory with compiled plugins, so the same bytecode is not compiled on every launch.
+///
+/// Compiled code is stored under a hash of the bytecode, compiler settings, fuel, memory limit,
+/// versions of scotch and wasmer and the target. Any change of them results in a new entry,
+/// outdated entries are never read again and can be removed with [`Self::clear`].
+/// ```ignore
+/// let plugin = WasmPlugin::builder()
+///     .with_cache(ModuleCache::new("target/plugin-cache"))
+///     .with_state(())
+///     .from_binary(PLUGIN_BYTES)?
+///     .finish()?;
+/// ```
+/// Compiled code is loaded without validation, the directory must only be writable by trusted users.
+#[derive(Debug, Clone)]
+pub struct ModuleCache {
+    dir: PathBuf,
+}
+
+impl ModuleCache {
+    /// Creates cache that keeps compiled plugins in `dir`, directory is created on first write.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Directory of the cache.
+    #[inline]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Removes every compiled plugin from the cache.
+    pub fn clear(&self) -> io::Result<()> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == EXTENSION) {
+                fs::remove_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Hash of everything that affects the compiled code.
+    pub(crate) fn key(bytecode: &[u8], config: &EngineConfig, memory_limit: Option<u32>) -> String {
+        let target = Target::default();
+
+        let mut hasher = Sha256::new();
+        hasher.update(env!("CARGO_PKG_VERSION"));
+        hasher.update(wasmer::VERSION);
+        hasher.update(format!(
+            "{} {:?} {config:?} {memory_limit:?}",
+            target.triple(),
+            target.cpu_features()
+        ));
+        hasher.update(bytecode);
+
+        hasher
+            .finalize()
+            .iter()
+            .fold(String::with_capacity(64), |mut key, byte| {
+                let _ = write!(key, "{byte:02x}");
+                key
+            })
+    }
+
+    /// Loads compiled plugin, `None` if it is missing or can not be deserialized.
+    pub(crate) fn load(&self, store: &Store, key: &str) -> Option<Module> {
+        // SAFETY: Entries are only written by `Self::store` under a key that includes
+        // wasmer version and the target, and the directory is expected to be trusted.
+        unsafe { Module::deserialize_from_file(store, self.path(key)).ok() }
+    }
+
+    /// Writes compiled plugin to the cache. Written to a temporary file first,
+    /// so concurrent loads never see a partially written entry.
+    pub(crate) fn store(&self, key: &str, module: &Module) -> io::Result<()> {
+        let bytes = module.serialize().map_err(io::Error::other)?;
+
+        fs::create_dir_all(&self.dir)?;
+        let temp = self.dir.join(format!("{key}.{}.tmp", std::process::id()));
+        fs::write(&temp, &bytes)?;
+        let renamed = fs::rename(&temp, self.path(key));
+        if renamed.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        renamed
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.{EXTENSION}"))
+    }
+}
@@ -0,0 +1,160 @@
+use crate::ScotchHostError;
+use bincode::error::{DecodeError, EncodeError};
+use wasmer::{AsStoreMut, Instance, InstantiationError};
+
+/// Identifies the format a plugin encodes values with, see [`Codec::ID`].
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodecId {
+    Bincode = 0,
+    Postcard = 1,
+    Json = 2,
+}
+
+impl CodecId {
+    /// Converts the id reported by the guest back to the codec.
+    pub fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Self::Bincode),
+            1 => Some(Self::Postcard),
+            2 => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Format used to encode values passed between host and guest.
+/// Codec is a type parameter of the macros, [`Bincode`] is used by default.
+/// ```ignore
+/// #[guest_functions(codec = scotch_host::Postcard)]
+/// extern "C" {
+///     pub fn add_up_list(nums: &Vec<i32>) -> i32;
+/// }
+/// ```
+/// Host and guest must use the same codec, see [`WasmPluginBuilder::with_codec`](crate::WasmPluginBuilder::with_codec).
+pub trait Codec: 'static {
+    /// Id the guest reports with `export_alloc!`.
+    const ID: CodecId;
+}
+
+/// Codec that can encode and decode `T`.
+pub trait Encodes<T>: Codec {
+    /// Encodes `value` into `buf`, returning the number of bytes written.
+    /// Fails if `buf` is too small.
+    fn encode_into_slice(value: &T, buf: &mut [u8]) -> Result<usize, EncodeError>;
+
+    fn encode_to_vec(value: &T) -> Result<Vec<u8>, EncodeError>;
+
+    fn decode_from_slice(bytes: &[u8]) -> Result<T, DecodeError>;
+}
+
+/// Makes sure that the guest was built with the `expected` codec.
+pub(crate) fn check_codec(
+    store: &mut impl AsStoreMut,
+    instance: &Instance,
+    expected: CodecId,
+) -> Result<(), ScotchHostError> {
+    let id = instance
+        .exports
+        .get_typed_function::<(), u32>(&*store, "__scotch_codec")
+        .map_err(ScotchHostError::CodecMissing)?
+        .call(store)
+        .map_err(InstantiationError::Start)?;
+
+    match CodecId::from_id(id) {
+        Some(codec) if codec == expected => Ok(()),
+        found => Err(ScotchHostError::CodecMismatch { expected, found }),
+    }
+}
+
+/// Types that can be passed between host and guest with the [`Bincode`] codec.
+/// Requires `bincode::Encode` and `bincode::Decode`, serde types can be wrapped
+/// into [`Serde`] with `serde` feature.
+pub trait Value: bincode::Encode + bincode::Decode {}
+
+impl<T: bincode::Encode + bincode::Decode> Value for T {}
+
+/// Wrapper that encodes serde types with the `bincode` codec, so they can be passed
+/// next to types that implement `bincode::Encode` and `bincode::Decode`.
+/// ```ignore
+/// fn greet(person: &Serde<Person>) -> String;
+/// ```
+#[cfg(feature = "serde")]
+#[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "serde")))]
+pub use bincode::serde::Compat as Serde;
+
+/// Encodes [`Value`]s with `bincode`, the default codec.
+pub struct Bincode;
+
+impl Codec for Bincode {
+    const ID: CodecId = CodecId::Bincode;
+}
+
+impl<T: Value> Encodes<T> for Bincode {
+    fn encode_into_slice(value: &T, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        bincode::encode_into_slice(value, buf, bincode::config::standard())
+    }
+
+    fn encode_to_vec(value: &T) -> Result<Vec<u8>, EncodeError> {
+        bincode::encode_to_vec(value, bincode::config::standard())
+    }
+
+    fn decode_from_slice(bytes: &[u8]) -> Result<T, DecodeError> {
+        bincode::decode_from_slice(bytes, bincode::config::standard()).map(|(val, _)| val)
+    }
+}
+
+/// Encodes serde types with `postcard`.
+#[cfg(feature = "postcard")]
+#[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "postcard")))]
+pub struct Postcard;
+
+#[cfg(feature = "postcard")]
+impl Codec for Postcard {
+    const ID: CodecId = CodecId::Postcard;
+}
+
+#[cfg(feature = "postcard")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> Encodes<T> for Postcard {
+    fn encode_into_slice(value: &T, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        postcard::to_slice(value, buf)
+            .map(|out| out.len())
+            .map_err(|e| EncodeError::OtherString(e.to_string()))
+    }
+
+    fn encode_to_vec(value: &T) -> Result<Vec<u8>, EncodeError> {
+        postcard::to_allocvec(value).map_err(|e| EncodeError::OtherString(e.to_string()))
+    }
+
+    fn decode_from_slice(bytes: &[u8]) -> Result<T, DecodeError> {
+        postcard::from_bytes(bytes).map_err(|e| DecodeError::OtherString(e.to_string()))
+    }
+}
+
+/// Encodes serde types with `serde_json`.
+#[cfg(feature = "json")]
+#[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "json")))]
+pub struct Json;
+
+#[cfg(feature = "json")]
+impl Codec for Json {
+    const ID: CodecId = CodecId::Json;
+}
+
+#[cfg(feature = "json")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> Encodes<T> for Json {
+    fn encode_into_slice(value: &T, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        let mut cursor = std::io::Cursor::new(buf);
+        serde_json::to_writer(&mut cursor, value)
+            .map(|_| cursor.position() as usize)
+            .map_err(|e| EncodeError::OtherString(e.to_string()))
+    }
+
+    fn encode_to_vec(value: &T) -> Result<Vec<u8>, EncodeError> {
+        serde_json::to_vec(value).map_err(|e| EncodeError::OtherString(e.to_string()))
+    }
+
+    fn decode_from_slice(bytes: &[u8]) -> Result<T, DecodeError> {
+        serde_json::from_slice(bytes).map_err(|e| DecodeError::OtherString(e.to_string()))
+    }
+}
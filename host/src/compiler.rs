@@ -0,0 +1,168 @@
+use wasmer::Engine;
+
+#[cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass"))]
+use crate::interrupt::Interrupt;
+#[cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass"))]
+use std::sync::Arc;
+#[cfg(not(any(feature = "cranelift", feature = "llvm", feature = "singlepass")))]
+use wasmer::EngineBuilder;
+#[cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass"))]
+use wasmer::{wasmparser::Operator, CompilerConfig};
+#[cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass"))]
+use wasmer_middlewares::Metering;
+
+/// Compiler that translates wasm bytecode to native code.
+/// Every compiler enabled with feature flags is available, default one is
+/// `Cranelift`, then `Llvm`, then `Singlepass`.
+/// ```ignore
+/// // Untrusted plugins that are loaded often, compilation time is linear.
+/// let plugin = WasmPlugin::builder()
+///     .with_compiler(Compiler::Singlepass)
+///     .with_state(())
+///     .from_binary(PLUGIN_BYTES)?
+///     .finish()?;
+/// ```
+#[cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass"))]
+#[cfg_attr(
+    feature = "unstable-doc-cfg",
+    doc(cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass")))
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compiler {
+    /// Fast compilation and good code, the default.
+    #[cfg(feature = "cranelift")]
+    Cranelift,
+    /// Slow compilation and the fastest code.
+    #[cfg(feature = "llvm")]
+    Llvm,
+    /// The fastest compilation and slower code, resistant to JIT bombs.
+    #[cfg(feature = "singlepass")]
+    Singlepass,
+}
+
+/// Compiler that `Store::default` would use.
+#[cfg(feature = "cranelift")]
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::Cranelift
+    }
+}
+
+/// Compiler that `Store::default` would use.
+#[cfg(all(feature = "llvm", not(feature = "cranelift")))]
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::Llvm
+    }
+}
+
+/// Compiler that `Store::default` would use.
+#[cfg(all(
+    feature = "singlepass",
+    not(any(feature = "cranelift", feature = "llvm"))
+))]
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::Singlepass
+    }
+}
+
+/// How much effort the compiler puts into optimizing the code.
+/// `Singlepass` does not optimize and ignores it.
+#[cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass"))]
+#[cfg_attr(
+    feature = "unstable-doc-cfg",
+    doc(cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass")))
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum OptLevel {
+    /// No optimizations, the fastest compilation.
+    None,
+    /// Optimize for speed of the code, the default.
+    #[default]
+    Speed,
+    /// Optimize for speed and size of the code.
+    SpeedAndSize,
+}
+
+#[cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass"))]
+impl Compiler {
+    /// Creates engine that compiles with this compiler,
+    /// it can be shared by many plugins with [`WasmPluginBuilder::new_with_engine`](crate::WasmPluginBuilder::new_with_engine).
+    pub fn engine(self, opt_level: OptLevel) -> Engine {
+        EngineConfig {
+            compiler: self,
+            opt_level,
+            fuel: None,
+        }
+        .engine()
+    }
+
+    fn config(self, opt_level: OptLevel) -> Box<dyn CompilerConfig> {
+        match self {
+            #[cfg(feature = "cranelift")]
+            Self::Cranelift => {
+                use wasmer::CraneliftOptLevel;
+
+                let mut config = wasmer::Cranelift::default();
+                config.opt_level(match opt_level {
+                    OptLevel::None => CraneliftOptLevel::None,
+                    OptLevel::Speed => CraneliftOptLevel::Speed,
+                    OptLevel::SpeedAndSize => CraneliftOptLevel::SpeedAndSize,
+                });
+                Box::new(config)
+            }
+            #[cfg(feature = "llvm")]
+            Self::Llvm => {
+                use wasmer::LLVMOptLevel;
+
+                let mut config = wasmer::LLVM::default();
+                config.opt_level(match opt_level {
+                    OptLevel::None => LLVMOptLevel::None,
+                    OptLevel::Speed => LLVMOptLevel::Aggressive,
+                    OptLevel::SpeedAndSize => LLVMOptLevel::Default,
+                });
+                Box::new(config)
+            }
+            #[cfg(feature = "singlepass")]
+            Self::Singlepass => {
+                let _ = opt_level;
+                Box::new(wasmer::Singlepass::default())
+            }
+        }
+    }
+}
+
+/// Settings that affect the code produced by the engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub(crate) struct EngineConfig {
+    #[cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass"))]
+    pub(crate) compiler: Compiler,
+    #[cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass"))]
+    pub(crate) opt_level: OptLevel,
+    /// Instruments the code with metering if set.
+    pub(crate) fuel: Option<u64>,
+}
+
+impl EngineConfig {
+    /// Engine with the selected compiler, instrumented with metering if `fuel` is set.
+    /// Code is always instrumented with interrupt checks, so timeouts and cancellation
+    /// work for guest calls that never call a host function.
+    #[cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass"))]
+    pub(crate) fn engine(&self) -> Engine {
+        let mut compiler = self.compiler.config(self.opt_level);
+        if let Some(fuel) = self.fuel {
+            compiler.push_middleware(Arc::new(Metering::new(fuel, |_: &Operator| 1)));
+        }
+        // Pushed after metering, so checks do not consume fuel.
+        compiler.push_middleware(Arc::new(Interrupt::default()));
+
+        compiler.into()
+    }
+
+    /// Headless engine, fuel can not be set without a compiler.
+    #[cfg(not(any(feature = "cranelift", feature = "llvm", feature = "singlepass")))]
+    pub(crate) fn engine(&self) -> Engine {
+        EngineBuilder::headless().engine()
+    }
+}
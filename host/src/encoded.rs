@@ -1,20 +1,23 @@
-use crate::{ManagedPtr, PrefixType, ScotchHostError};
-use bincode::{config::standard, Decode, Encode};
-use std::{borrow::Cow, marker::PhantomData, mem::size_of};
+use crate::{
+    header::{Header, CELL_SIZE, HEADER_SIZE},
+    memory::{alloc_in, free_in},
+    Bincode, Encodes, GuestMemory, ManagedPtr, ScotchHostError,
+};
+use std::{borrow::Cow, marker::PhantomData};
 use wasmer::{
-    AsStoreMut, FromToNativeWasmType, Instance, Memory32, MemorySize, MemoryView,
+    AsStoreMut, FromToNativeWasmType, Instance, MemoryAccessError, MemorySize, MemoryView,
     NativeWasmTypeInto,
 };
 
 #[doc(hidden)]
-pub struct EncodedPtr<T: Encode + Decode, M: MemorySize = Memory32> {
+pub struct EncodedPtr<T, M: MemorySize = GuestMemory, C = Bincode> {
     offset: M::Offset,
     size: usize,
-    _ty: PhantomData<T>,
+    _ty: PhantomData<fn() -> (T, C)>,
 }
 
-impl<T: Encode + Decode, M: MemorySize> EncodedPtr<T, M> {
-    pub fn to_managed(&self) -> ManagedPtr<T, M> {
+impl<T, M: MemorySize, C: Encodes<T>> EncodedPtr<T, M, C> {
+    pub fn to_managed(&self) -> ManagedPtr<T, M, C> {
         ManagedPtr::new(self.offset)
     }
 
@@ -27,40 +30,21 @@ impl<T: Encode + Decode, M: MemorySize> EncodedPtr<T, M> {
 
         // First try encoding to the stack if the object is small,
         // otherwise encode to the heap.
-        let buf: Cow<[u8]> =
-            if let Ok(size) = bincode::encode_into_slice(value, &mut buf[..], standard()) {
-                Cow::Borrowed(&buf[..size])
-            } else {
-                Cow::Owned(bincode::encode_to_vec(value, standard())?)
-            };
-
-        let func = instance
-            .exports
-            .get_function("__scotch_alloc")
-            .map_err(ScotchHostError::AllocMissing)?;
-        let out = &func
-            .call(
-                store,
-                &[
-                    ((buf.len() + size_of::<PrefixType>()) as i32).into(),
-                    1i32.into(),
-                ],
-            )
-            .map_err(ScotchHostError::AllocFailed)?[0];
-
-        #[cfg(feature = "mem64")]
-        let ptr = out.unwrap_i64() as u64;
-        #[cfg(not(feature = "mem64"))]
-        let ptr = out.unwrap_i32() as u64;
+        let buf: Cow<[u8]> = if let Ok(size) = C::encode_into_slice(value, &mut buf[..]) {
+            Cow::Borrowed(&buf[..size])
+        } else {
+            Cow::Owned(C::encode_to_vec(value)?)
+        };
 
+        let ptr = alloc_in(buf.len() + HEADER_SIZE, store, instance)?;
         let view = instance
             .exports
             .get_memory("memory")
             .map_err(ScotchHostError::MemoryMissing)?
             .view(store);
 
-        view.write(ptr, &(buf.len() as PrefixType).to_le_bytes())?;
-        view.write(ptr + size_of::<PrefixType>() as u64, &buf[..])?;
+        view.write(ptr, &Header::new(buf.len()).to_bytes())?;
+        view.write(ptr + HEADER_SIZE as u64, &buf[..])?;
 
         if let Ok(offset) = ptr.try_into() {
             Ok(EncodedPtr {
@@ -78,40 +62,21 @@ impl<T: Encode + Decode, M: MemorySize> EncodedPtr<T, M> {
         store: &mut impl AsStoreMut,
         instance: &Instance,
     ) -> Result<(), ScotchHostError> {
-        let offset: u64 = self.offset.into();
-
-        let func = instance
-            .exports
-            .get_function("__scotch_free")
-            .map_err(ScotchHostError::FreeMissing)?;
-        func.call(
-            store,
-            &[
-                (offset as i32).into(),
-                ((self.size + size_of::<PrefixType>()) as i32).into(),
-                1i32.into(),
-            ],
-        )
-        .map(|_| ())
-        .map_err(ScotchHostError::FreeFailed)
+        free_in(self.offset.into(), self.size + HEADER_SIZE, store, instance)
     }
 
     pub fn read(&self, view: &MemoryView) -> Result<T, ScotchHostError> {
         let offset: u64 = self.offset.into();
-        let mut size = [0, 0];
-
-        view.read(offset, &mut size)?;
-
-        let len = u16::from_le_bytes(size) as usize;
-        let mut data = vec![0; len];
+        let header = Header::read(view, offset)?;
+        let mut data = vec![0; header.len as usize];
 
-        view.read(offset + 2, &mut data[..])?;
+        view.read(offset + HEADER_SIZE as u64, &mut data[..])?;
 
-        Ok(bincode::decode_from_slice(&data[..], standard()).map(|(val, _)| val)?)
+        Ok(C::decode_from_slice(&data[..])?)
     }
 }
 
-unsafe impl<T: Encode + Decode, M: MemorySize> FromToNativeWasmType for EncodedPtr<T, M>
+unsafe impl<T, M: MemorySize, C> FromToNativeWasmType for EncodedPtr<T, M, C>
 where
     M::Native: NativeWasmTypeInto,
 {
@@ -132,15 +97,106 @@ where
     }
 }
 
-impl<T: Encode + Decode, M: MemorySize> Clone for EncodedPtr<T, M> {
+impl<T, M: MemorySize, C> Clone for EncodedPtr<T, M, C> {
     #[inline]
     fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, M: MemorySize, C> Copy for EncodedPtr<T, M, C> {}
+
+/// Pointer to a cell with the address of an envelope. Used to pass `&mut T` to the guest,
+/// guest replaces the envelope in the cell with the modified value.
+#[doc(hidden)]
+pub struct EncodedMutPtr<T, M: MemorySize = GuestMemory, C = Bincode> {
+    offset: M::Offset,
+    _ty: PhantomData<fn() -> (T, C)>,
+}
+
+impl<T, M: MemorySize, C: Encodes<T>> EncodedMutPtr<T, M, C> {
+    pub fn new_in(
+        value: &T,
+        store: &mut impl AsStoreMut,
+        instance: &Instance,
+    ) -> Result<Self, ScotchHostError> {
+        let envelope: u64 = EncodedPtr::<T, M, C>::new_in(value, store, instance)?
+            .offset
+            .into();
+        let cell = alloc_in(CELL_SIZE, store, instance)?;
+
+        instance
+            .exports
+            .get_memory("memory")
+            .map_err(ScotchHostError::MemoryMissing)?
+            .view(store)
+            .write(cell, &envelope.to_le_bytes())?;
+
+        Ok(Self {
+            offset: cell.try_into().map_err(|_| MemoryAccessError::Overflow)?,
+            _ty: PhantomData,
+        })
+    }
+
+    /// Reads the value guest has written back and frees both the envelope and the cell.
+    pub fn read_back_in(
+        self,
+        store: &mut impl AsStoreMut,
+        instance: &Instance,
+    ) -> Result<T, ScotchHostError> {
+        let cell: u64 = self.offset.into();
+
+        let (ptr, value, len) = {
+            let view = instance
+                .exports
+                .get_memory("memory")
+                .map_err(ScotchHostError::MemoryMissing)?
+                .view(store);
+
+            let mut buf = [0; CELL_SIZE];
+            view.read(cell, &mut buf)?;
+
+            let envelope = u64::from_le_bytes(buf)
+                .try_into()
+                .map_err(|_| MemoryAccessError::Overflow)?;
+            let ptr = ManagedPtr::<T, M, C>::new(envelope);
+            let (value, len) = ptr.read(&view)?;
+
+            (ptr, value, len)
+        };
+
+        ptr.free_in(len, store, instance)?;
+        free_in(cell, CELL_SIZE, store, instance)?;
+
+        Ok(value)
+    }
+}
+
+unsafe impl<T, M: MemorySize, C> FromToNativeWasmType for EncodedMutPtr<T, M, C>
+where
+    M::Native: NativeWasmTypeInto,
+{
+    type Native = M::Native;
+
+    #[inline]
+    fn from_native(native: Self::Native) -> Self {
         Self {
-            offset: self.offset,
+            offset: M::native_to_offset(native),
             _ty: PhantomData,
-            size: self.size,
         }
     }
+
+    #[inline]
+    fn to_native(self) -> Self::Native {
+        M::offset_to_native(self.offset)
+    }
+}
+
+impl<T, M: MemorySize, C> Clone for EncodedMutPtr<T, M, C> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
 }
 
-impl<T: Encode + Decode, M: MemorySize> Copy for EncodedPtr<T, M> {}
+impl<T, M: MemorySize, C> Copy for EncodedMutPtr<T, M, C> {}
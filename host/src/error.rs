@@ -1,10 +1,15 @@
 use std::{
     error::Error,
     fmt::{self, Display},
+    io,
 };
 
+use crate::CodecId;
 use bincode::error::{DecodeError, EncodeError};
-use wasmer::{ExportError, MemoryAccessError, RuntimeError};
+use wasmer::{
+    CompileError, DeserializeError, ExportError, InstantiationError, MemoryAccessError,
+    RuntimeError,
+};
 
 /// Error for everything that can go wrong.
 #[derive(Debug)]
@@ -17,6 +22,56 @@ pub enum ScotchHostError {
     MemoryMissing(ExportError),
     AllocMissing(ExportError),
     FreeMissing(ExportError),
+    /// Value in linear memory does not start with a valid envelope header.
+    InvalidEnvelope,
+    /// Host and guest were built with different envelope layouts.
+    EnvelopeVersionMismatch {
+        expected: u8,
+        found: u8,
+    },
+    InstantiationFailed(Box<InstantiationError>),
+    /// Plugin does not export `__scotch_codec`, most likely `export_alloc!` is missing.
+    CodecMissing(ExportError),
+    /// Plugin or one of the guest function handles uses a different codec than the one set
+    /// with `with_codec`. `found` is `None` if guest reported an unknown codec.
+    CodecMismatch {
+        expected: CodecId,
+        found: Option<CodecId>,
+    },
+    /// Guest call ran out of fuel.
+    FuelExhausted,
+    /// Plugin was not compiled with fuel metering, e.g. it was created by a shared engine
+    /// or deserialized from a module compiled without fuel.
+    MeteringMissing(ExportError),
+    /// Plugin ran out of memory, usually because of the limit set with `with_memory_limit`.
+    MemoryLimitExceeded,
+    /// Guest call took longer than the timeout set with `with_timeout` or `with_call_timeout`.
+    Timeout,
+    /// Plugin has a timeout but was not compiled with interrupt checks, e.g. its engine was
+    /// not created by scotch or it was deserialized from a module compiled by another engine.
+    InterruptMissing,
+    /// Future returned by `call_async` was dropped while the call was running,
+    /// or its task was cancelled by tokio before it finished.
+    #[cfg(feature = "async")]
+    Cancelled,
+    /// Plugin has no state of the expected type, e.g. `configure` of
+    /// [`PollingHotPlugin`](crate::PollingHotPlugin) did not call `with_state`.
+    StateMissing,
+    /// Failed to read a plugin from disk.
+    Io(io::Error),
+    CompileFailed(Box<CompileError>),
+    DeserializeFailed(Box<DeserializeError>),
+    /// Invalid WASI configuration, e.g. mapped directory does not exist.
+    #[cfg(feature = "wasi")]
+    WasiStateCreationFailed(Box<wasmer_wasi::WasiStateCreationError>),
+    #[cfg(feature = "wasi")]
+    WasiFailed(Box<wasmer_wasi::WasiError>),
+    /// Serialized plugin was rejected by `from_verified_serialized`.
+    #[cfg(feature = "verify")]
+    VerificationFailed(Box<crate::VerificationError>),
+    /// Plugin is unsigned or not signed by a key from the trust store set with `with_trust_store`.
+    #[cfg(feature = "signing")]
+    SignatureRejected(crate::SignatureError),
 }
 
 impl Display for ScotchHostError {
@@ -27,6 +82,54 @@ impl Display for ScotchHostError {
 
 impl Error for ScotchHostError {}
 
+impl From<ScotchHostError> for RuntimeError {
+    #[inline]
+    fn from(v: ScotchHostError) -> Self {
+        RuntimeError::user(Box::new(v))
+    }
+}
+
+/// Error of a guest function that returns `Result<T, E>`.
+/// Combines errors returned by the guest with failures of the call itself.
+#[derive(Debug)]
+pub enum GuestError<E> {
+    /// Guest function returned `Err`.
+    Guest(E),
+    /// Call failed, e.g. guest trapped or panicked.
+    Runtime(RuntimeError),
+}
+
+impl<E: fmt::Debug> Display for GuestError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Guest(e) => write!(f, "Guest returned an error: {e:?}"),
+            Self::Runtime(e) => write!(f, "Guest call failed: {e}"),
+        }
+    }
+}
+
+impl<E: fmt::Debug> Error for GuestError<E> {}
+
+impl<E> From<RuntimeError> for GuestError<E> {
+    #[inline]
+    fn from(v: RuntimeError) -> Self {
+        Self::Runtime(v)
+    }
+}
+
+/// Extension for results of guest functions that return `Result<T, E>`.
+pub trait GuestResultExt<T, E> {
+    /// Flattens `Result<Result<T, E>, RuntimeError>` into `Result<T, GuestError<E>>`.
+    fn flatten_guest(self) -> Result<T, GuestError<E>>;
+}
+
+impl<T, E> GuestResultExt<T, E> for Result<Result<T, E>, RuntimeError> {
+    #[inline]
+    fn flatten_guest(self) -> Result<T, GuestError<E>> {
+        self?.map_err(GuestError::Guest)
+    }
+}
+
 macro_rules! impl_from {
     ($target:ident, $($var:ident : $type:ty),*$(,)?) => {
         $(
@@ -37,7 +140,18 @@ macro_rules! impl_from {
                 }
             }
         )*
-    }
+    };
+    // Large errors are boxed to keep `Result<T, ScotchHostError>` small.
+    ($target:ident, boxed $($var:ident : $type:ty),*$(,)?) => {
+        $(
+            impl From<$type> for $target {
+                #[inline]
+                fn from(v: $type) -> Self {
+                    Self::$var(Box::new(v))
+                }
+            }
+        )*
+    };
 }
 
 impl_from!(
@@ -45,4 +159,31 @@ impl_from!(
     EncodingFailed: EncodeError,
     DecodingFailed: DecodeError,
     MemoryAccessFailed: MemoryAccessError,
+    Io: io::Error,
+);
+
+impl_from!(
+    ScotchHostError,
+    boxed InstantiationFailed: InstantiationError,
+    CompileFailed: CompileError,
+    DeserializeFailed: DeserializeError,
+);
+
+#[cfg(feature = "verify")]
+impl_from!(
+    ScotchHostError,
+    boxed VerificationFailed: crate::VerificationError,
+);
+
+#[cfg(feature = "signing")]
+impl_from!(
+    ScotchHostError,
+    SignatureRejected: crate::SignatureError,
+);
+
+#[cfg(feature = "wasi")]
+impl_from!(
+    ScotchHostError,
+    boxed WasiStateCreationFailed: wasmer_wasi::WasiStateCreationError,
+    WasiFailed: wasmer_wasi::WasiError,
 );
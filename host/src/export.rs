@@ -1,18 +1,18 @@
-use parking_lot::RwLock;
+use crate::{CodecId, PluginStore};
 use std::{
     any::{Any, TypeId},
     sync::Arc,
 };
 
 #[doc(hidden)]
-pub use wasmer::{Exports, Instance, RuntimeError, Store, TypedFunction};
+pub use wasmer::{Exports, Instance, Memory32, RuntimeError, Store, TypedFunction};
 
 #[doc(hidden)]
-pub type StoreRef = Arc<RwLock<Store>>;
+pub type StoreRef = Arc<PluginStore>;
 #[doc(hidden)]
 pub type InstanceRef = Arc<Instance>;
 #[doc(hidden)]
-pub type CallbackRef = Box<dyn Any>;
+pub type CallbackRef = Box<dyn Any + Send + Sync>;
 
 #[doc(hidden)]
 /// Do not implemented this trait manually.
@@ -28,4 +28,7 @@ pub unsafe trait GuestFunctionCreator {
         Self: Sized;
 
     fn create(&self, store: StoreRef, instance: InstanceRef) -> Option<(TypeId, CallbackRef)>;
+
+    /// Codec the handle encodes arguments with.
+    fn codec(&self) -> CodecId;
 }
@@ -0,0 +1,167 @@
+use std::{
+    future::Future,
+    pin::pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+};
+
+#[cfg(feature = "async")]
+use crate::{GuestFunctionHandle, PluginStore, ScotchHostError, WasmPlugin};
+#[cfg(feature = "async")]
+use std::{
+    ops::Deref,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+};
+#[cfg(feature = "async")]
+use tokio::task::JoinHandle;
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Drives future of an async host function to completion on the current thread.
+#[doc(hidden)]
+pub fn block_on<T, F: Future<Output = T>>(future: F) -> T {
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(out) => return out,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "async")))]
+impl WasmPlugin {
+    /// Calls `call` with the guest function on the blocking thread pool of tokio,
+    /// so neither the guest nor async host functions block an executor thread.
+    /// Dropping the returned future cancels the call, see [`GuestCall`].
+    /// ```ignore
+    /// let plugin = Arc::new(plugin);
+    /// let sum = plugin.call_async::<add_up_list, _>(|f| f(&vec![1, 2, 3])).await??;
+    /// ```
+    /// # Panics
+    /// If function was not cached with `make_exports!` or if called outside of tokio runtime.
+    pub fn call_async<H: GuestFunctionHandle + 'static, R: Send + 'static>(
+        self: &Arc<Self>,
+        call: impl FnOnce(&H::Callback) -> R + Send + 'static,
+    ) -> GuestCall<R> {
+        assert!(self.function::<H>().is_some(), "Function not found");
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let task = tokio::task::spawn_blocking({
+            let plugin = self.clone();
+            let cancelled = cancelled.clone();
+
+            move || {
+                if cancelled.load(Ordering::Acquire) {
+                    return None;
+                }
+
+                let function = plugin.function_unwrap::<H>();
+                Some(PluginStore::with_cancellation(cancelled, || call(function)))
+            }
+        });
+
+        GuestCall { task, cancelled }
+    }
+}
+
+/// Guest call running on the blocking thread pool, created by [`WasmPlugin::call_async`].
+///
+/// Dropping the future before it completes cancels the call. A call that has not started yet
+/// is skipped, otherwise it traps with [`ScotchHostError::Cancelled`] at the next interrupt check
+/// or host function, the same way as a call that timed out. Other calls to the plugin are not affected.
+/// Plugins that were not compiled with interrupt checks are only cancelled in host functions,
+/// see [`WasmPluginBuilder::with_timeout`](crate::WasmPluginBuilder::with_timeout).
+///
+/// Resolves to [`ScotchHostError::Cancelled`] if the task of the call was cancelled
+/// by tokio, e.g. because the runtime shut down. Panics of the call are resumed.
+#[cfg(feature = "async")]
+#[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "async")))]
+pub struct GuestCall<R> {
+    task: JoinHandle<Option<R>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+#[cfg(feature = "async")]
+impl<R> Future for GuestCall<R> {
+    type Output = Result<R, ScotchHostError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match Pin::new(&mut self.get_mut().task).poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Ok(out)) => Poll::Ready(out.ok_or(ScotchHostError::Cancelled)),
+            Poll::Ready(Err(e)) => match e.try_into_panic() {
+                Ok(panic) => std::panic::resume_unwind(panic),
+                Err(_) => Poll::Ready(Err(ScotchHostError::Cancelled)),
+            },
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<R> Drop for GuestCall<R> {
+    fn drop(&mut self) {
+        // Finished calls are not affected.
+        self.cancelled.store(true, Ordering::Release);
+    }
+}
+
+/// Plugin that is called from async code, cheap to clone.
+/// ```ignore
+/// let plugin = AsyncWasmPlugin::new(plugin);
+/// let sum = plugin.call::<add_up_list, _>(|f| f(&vec![1, 2, 3])).await??;
+/// ```
+#[cfg(feature = "async")]
+#[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "async")))]
+#[derive(Clone)]
+pub struct AsyncWasmPlugin {
+    plugin: Arc<WasmPlugin>,
+}
+
+#[cfg(feature = "async")]
+impl AsyncWasmPlugin {
+    pub fn new(plugin: impl Into<Arc<WasmPlugin>>) -> Self {
+        Self {
+            plugin: plugin.into(),
+        }
+    }
+
+    /// Calls guest function on the blocking thread pool, see [`WasmPlugin::call_async`].
+    /// # Panics
+    /// If function was not cached with `make_exports!` or if called outside of tokio runtime.
+    #[inline]
+    pub fn call<H: GuestFunctionHandle + 'static, R: Send + 'static>(
+        &self,
+        call: impl FnOnce(&H::Callback) -> R + Send + 'static,
+    ) -> GuestCall<R> {
+        self.plugin.call_async::<H, R>(call)
+    }
+
+    /// Underlying plugin, calling it directly blocks the current thread.
+    #[inline]
+    pub fn plugin(&self) -> &Arc<WasmPlugin> {
+        &self.plugin
+    }
+}
+
+#[cfg(feature = "async")]
+impl Deref for AsyncWasmPlugin {
+    type Target = WasmPlugin;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.plugin
+    }
+}
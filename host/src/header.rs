@@ -0,0 +1,78 @@
+use crate::ScotchHostError;
+use wasmer::{MemoryAccessError, MemoryView};
+
+/// Magic bytes every envelope starts with.
+const MAGIC: [u8; 2] = *b"SC";
+
+/// Version of the envelope layout. Must match with `scotch_guest::ENVELOPE_VERSION`.
+pub const ENVELOPE_VERSION: u8 = 1;
+
+/// Size of the header that precedes every value in linear memory.
+pub(crate) const HEADER_SIZE: usize = 12;
+
+/// Size of the cell that holds an address of an envelope as `u64`.
+/// Cells are used to pass `&mut T` so the callee can replace the value.
+pub(crate) const CELL_SIZE: usize = 8;
+
+/// Header that precedes every value passed between host and guest.
+///
+/// Layout (little endian):
+/// * `0..2` - magic bytes `SC`.
+/// * `2` - envelope version.
+/// * `3` - reserved, always `0`.
+/// * `4..12` - length of the payload as `u64`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Header {
+    pub(crate) len: u64,
+}
+
+impl Header {
+    #[inline]
+    pub(crate) fn new(len: usize) -> Self {
+        Self { len: len as u64 }
+    }
+
+    pub(crate) fn to_bytes(self) -> [u8; HEADER_SIZE] {
+        let mut out = [0; HEADER_SIZE];
+        out[..2].copy_from_slice(&MAGIC);
+        out[2] = ENVELOPE_VERSION;
+        out[4..].copy_from_slice(&self.len.to_le_bytes());
+        out
+    }
+
+    pub(crate) fn from_bytes(bytes: [u8; HEADER_SIZE]) -> Result<Self, ScotchHostError> {
+        if bytes[..2] != MAGIC {
+            return Err(ScotchHostError::InvalidEnvelope);
+        }
+
+        if bytes[2] != ENVELOPE_VERSION {
+            return Err(ScotchHostError::EnvelopeVersionMismatch {
+                expected: ENVELOPE_VERSION,
+                found: bytes[2],
+            });
+        }
+
+        let mut len = [0; 8];
+        len.copy_from_slice(&bytes[4..]);
+
+        Ok(Self {
+            len: u64::from_le_bytes(len),
+        })
+    }
+
+    /// Reads and validates the header at `offset`.
+    /// Also makes sure that the payload fits into the memory.
+    pub(crate) fn read(view: &MemoryView, offset: u64) -> Result<Self, ScotchHostError> {
+        let mut buf = [0; HEADER_SIZE];
+        view.read(offset, &mut buf)?;
+
+        let header = Self::from_bytes(buf)?;
+        let end = offset
+            .checked_add(HEADER_SIZE as u64)
+            .and_then(|start| start.checked_add(header.len));
+        match end {
+            Some(end) if end <= view.data_size() => Ok(header),
+            _ => Err(MemoryAccessError::HeapOutOfBounds.into()),
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use wasmer::Module;
+
+#[cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass"))]
+pub(crate) use middleware::Interrupt;
+
+/// Host function that compiled guest code calls to check whether the call was interrupted.
+pub(crate) const IMPORT_MODULE: &str = "scotch";
+pub(crate) const IMPORT_NAME: &str = "__scotch_interrupt";
+
+/// Whether `module` was compiled with [`Interrupt`], so calls that never reach
+/// a host function can still be interrupted.
+pub(crate) fn is_interruptible(module: &Module) -> bool {
+    module
+        .imports()
+        .functions()
+        .any(|import| import.module() == IMPORT_MODULE && import.name() == IMPORT_NAME)
+}
+
+#[cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass"))]
+mod middleware {
+    use super::{IMPORT_MODULE, IMPORT_NAME};
+    use parking_lot::Mutex;
+    use std::mem;
+    use wasmer::{
+        wasmparser::{Operator, Type as WpType, TypeOrFuncType},
+        ExportIndex, FunctionMiddleware, FunctionType, GlobalInit, GlobalType, LocalFunctionIndex,
+        MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Mutability, Type,
+    };
+    use wasmer_types::{FunctionIndex, GlobalIndex, ImportIndex, ImportKey, ModuleInfo};
+
+    /// Function entries and loop iterations between two checks.
+    const CHECK_INTERVAL: i32 = 10_000;
+
+    /// Makes guest code call the interrupt host function every [`CHECK_INTERVAL`] function
+    /// entries and loop iterations, so a call that timed out or was cancelled traps there.
+    ///
+    /// The host function is appended to the imported functions, so indexes of the functions
+    /// defined by the module are shifted by one. An engine compiles one module at a time,
+    /// the indexes of the module that is being compiled are kept until the next one.
+    #[derive(Debug, Default)]
+    pub(crate) struct Interrupt {
+        indexes: Mutex<Option<Indexes>>,
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    struct Indexes {
+        /// Imported host function, functions of the module start right after it.
+        function: FunctionIndex,
+        /// Global that counts down to the next check.
+        countdown: GlobalIndex,
+    }
+
+    impl Indexes {
+        fn shift(&self, index: &mut FunctionIndex) {
+            if *index >= self.function {
+                *index = FunctionIndex::from_u32(index.as_u32() + 1);
+            }
+        }
+
+        fn shifted(&self, index: u32) -> u32 {
+            let mut index = FunctionIndex::from_u32(index);
+            self.shift(&mut index);
+            index.as_u32()
+        }
+    }
+
+    impl ModuleMiddleware for Interrupt {
+        fn generate_function_middleware(
+            &self,
+            _: LocalFunctionIndex,
+        ) -> Box<dyn FunctionMiddleware> {
+            let indexes = self
+                .indexes
+                .lock()
+                .expect("Module info is transformed before functions are compiled");
+            Box::new(FunctionInterrupt {
+                indexes,
+                entered: false,
+            })
+        }
+
+        fn transform_module_info(&self, info: &mut ModuleInfo) {
+            let function = FunctionIndex::from_u32(info.num_imported_functions as u32);
+            let indexes = Indexes {
+                function,
+                countdown: info
+                    .globals
+                    .push(GlobalType::new(Type::I32, Mutability::Var)),
+            };
+            info.global_initializers
+                .push(GlobalInit::I32Const(CHECK_INTERVAL));
+
+            let signature = match info
+                .signatures
+                .iter()
+                .find(|(_, ty)| ty.params().is_empty() && ty.results().is_empty())
+            {
+                Some((index, _)) => index,
+                None => info.signatures.push(FunctionType::new([], [])),
+            };
+            let mut functions = info.functions.values().copied().collect::<Vec<_>>();
+            functions.insert(function.as_u32() as usize, signature);
+            info.functions = functions.into_iter().collect();
+
+            // Imports are resolved in order, so the last function import gets the last index.
+            let import_idx = info.imports.len() as u32;
+            info.imports.insert(
+                ImportKey {
+                    module: IMPORT_MODULE.into(),
+                    field: IMPORT_NAME.into(),
+                    import_idx,
+                },
+                ImportIndex::Function(function),
+            );
+            info.num_imported_functions += 1;
+
+            for export in info.exports.values_mut() {
+                if let ExportIndex::Function(index) = export {
+                    indexes.shift(index);
+                }
+            }
+            if let Some(index) = info.start_function.as_mut() {
+                indexes.shift(index);
+            }
+            for init in &mut info.table_initializers {
+                init.elements
+                    .iter_mut()
+                    .for_each(|index| indexes.shift(index));
+            }
+            for elements in info.passive_elements.values_mut() {
+                elements.iter_mut().for_each(|index| indexes.shift(index));
+            }
+            for init in info.global_initializers.values_mut() {
+                if let GlobalInit::RefFunc(index) = init {
+                    indexes.shift(index);
+                }
+            }
+            info.function_names = mem::take(&mut info.function_names)
+                .into_iter()
+                .map(|(mut index, name)| {
+                    indexes.shift(&mut index);
+                    (index, name)
+                })
+                .collect();
+
+            *self.indexes.lock() = Some(indexes);
+        }
+    }
+
+    #[derive(Debug)]
+    struct FunctionInterrupt {
+        indexes: Indexes,
+        /// Set once the check at the start of the function was emitted.
+        entered: bool,
+    }
+
+    impl FunctionInterrupt {
+        fn check(&self, state: &mut MiddlewareReaderState<'_>) {
+            let countdown = self.indexes.countdown.as_u32();
+            state.extend(&[
+                // if --countdown == 0 { countdown = CHECK_INTERVAL; interrupt(); }
+                Operator::GlobalGet {
+                    global_index: countdown,
+                },
+                Operator::I32Const { value: 1 },
+                Operator::I32Sub,
+                Operator::GlobalSet {
+                    global_index: countdown,
+                },
+                Operator::GlobalGet {
+                    global_index: countdown,
+                },
+                Operator::I32Eqz,
+                Operator::If {
+                    ty: TypeOrFuncType::Type(WpType::EmptyBlockType),
+                },
+                Operator::I32Const {
+                    value: CHECK_INTERVAL,
+                },
+                Operator::GlobalSet {
+                    global_index: countdown,
+                },
+                Operator::Call {
+                    function_index: self.indexes.function.as_u32(),
+                },
+                Operator::End,
+            ]);
+        }
+    }
+
+    impl FunctionMiddleware for FunctionInterrupt {
+        fn feed<'a>(
+            &mut self,
+            operator: Operator<'a>,
+            state: &mut MiddlewareReaderState<'a>,
+        ) -> Result<(), MiddlewareError> {
+            if !self.entered {
+                self.entered = true;
+                self.check(state);
+            }
+
+            match operator {
+                Operator::Call { function_index } => state.push_operator(Operator::Call {
+                    function_index: self.indexes.shifted(function_index),
+                }),
+                Operator::ReturnCall { function_index } => {
+                    state.push_operator(Operator::ReturnCall {
+                        function_index: self.indexes.shifted(function_index),
+                    })
+                }
+                Operator::RefFunc { function_index } => state.push_operator(Operator::RefFunc {
+                    function_index: self.indexes.shifted(function_index),
+                }),
+                Operator::Loop { .. } => {
+                    state.push_operator(operator);
+                    self.check(state);
+                }
+                _ => state.push_operator(operator),
+            }
+
+            Ok(())
+        }
+    }
+}
@@ -1,5 +1,42 @@
 #![cfg_attr(feature = "unstable-doc-cfg", feature(doc_cfg))]
-pub(crate) type PrefixType = u16;
+
+/// `usize` of the guest. Can be `u32` or `u64`.
+#[cfg(not(feature = "mem64"))]
+pub type GuestUsize = u32;
+
+/// `isize` of the guest. Can be `i32` or `i64`.
+#[cfg(not(feature = "mem64"))]
+pub type GuestIsize = i32;
+
+/// `usize` of the guest. Can be `u32` or `u64`.
+#[cfg(feature = "mem64")]
+pub type GuestUsize = u64;
+
+/// `isize` of the guest. Can be `i32` or `i64`.
+#[cfg(feature = "mem64")]
+pub type GuestIsize = i64;
+
+/// Linear memory of the guest. Can be `Memory32` or `Memory64`.
+#[cfg(not(feature = "mem64"))]
+pub type GuestMemory = wasmer::Memory32;
+
+/// Linear memory of the guest. Can be `Memory32` or `Memory64`.
+#[cfg(feature = "mem64")]
+pub type GuestMemory = wasmer::Memory64;
+
+mod header;
+pub use header::ENVELOPE_VERSION;
+
+mod codec;
+#[cfg(feature = "json")]
+pub use codec::Json;
+#[cfg(feature = "postcard")]
+pub use codec::Postcard;
+#[cfg(feature = "serde")]
+pub use codec::Serde;
+pub use codec::{Bincode, Codec, CodecId, Encodes, Value};
+
+mod memory;
 
 mod encoded;
 pub use encoded::*;
@@ -7,9 +44,62 @@ pub use encoded::*;
 mod managed;
 pub use managed::*;
 
+mod bytes;
+pub use bytes::*;
+
+mod tunables;
+
+mod compiler;
+#[cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass"))]
+pub use compiler::{Compiler, OptLevel};
+
+mod interrupt;
+
+mod store;
+pub use store::{PluginStore, RunningCall};
+
+mod watchdog;
+
 mod plugin;
 pub use plugin::*;
 
+mod manager;
+pub use manager::*;
+
+mod pool;
+pub use pool::*;
+
+mod template;
+pub use template::*;
+
+#[cfg(feature = "compiler")]
+mod reload;
+#[cfg(feature = "compiler")]
+pub use reload::*;
+
+#[cfg(feature = "cache")]
+mod cache;
+#[cfg(feature = "cache")]
+pub use cache::ModuleCache;
+
+#[cfg(feature = "verify")]
+mod verify;
+#[cfg(feature = "verify")]
+pub use verify::{ArtifactSigner, ArtifactVerifier, VerificationError};
+
+#[cfg(feature = "signing")]
+mod signing;
+#[cfg(feature = "signing")]
+pub use signing::{sign_plugin, PluginSignature, SignatureError, TrustStore};
+
+#[cfg(feature = "wasi")]
+mod wasi;
+#[cfg(feature = "wasi")]
+pub use wasi::WasiConfig;
+
+mod future;
+pub use future::*;
+
 mod export;
 pub use export::*;
 
@@ -1,18 +1,21 @@
-use crate::{PrefixType, ScotchHostError};
-use bincode::{config::standard, Decode, Encode};
-use std::{marker::PhantomData, mem::size_of};
+use crate::{
+    header::{Header, CELL_SIZE, HEADER_SIZE},
+    memory::free_in,
+    Bincode, EncodedPtr, Encodes, GuestMemory, ScotchHostError,
+};
+use std::marker::PhantomData;
 use wasmer::{
-    AsStoreMut, FromToNativeWasmType, Instance, Memory32, MemorySize, MemoryView,
+    AsStoreMut, FromToNativeWasmType, Instance, MemoryAccessError, MemorySize, MemoryView,
     NativeWasmTypeInto,
 };
 
 #[doc(hidden)]
-pub struct ManagedPtr<T: Encode + Decode, M: MemorySize = Memory32> {
+pub struct ManagedPtr<T, M: MemorySize = GuestMemory, C = Bincode> {
     offset: M::Offset,
-    _ty: PhantomData<T>,
+    _ty: PhantomData<fn() -> (T, C)>,
 }
 
-impl<T: Encode + Decode, M: MemorySize> ManagedPtr<T, M> {
+impl<T, M: MemorySize, C: Encodes<T>> ManagedPtr<T, M, C> {
     pub(crate) fn new(offset: M::Offset) -> Self {
         Self {
             offset,
@@ -22,25 +25,16 @@ impl<T: Encode + Decode, M: MemorySize> ManagedPtr<T, M> {
 
     pub fn read(&self, view: &MemoryView) -> Result<(T, usize), ScotchHostError> {
         let offset: u64 = self.offset.into();
-        let mut buf = [0; size_of::<PrefixType>()];
-        view.read(offset, &mut buf)?;
+        let len = Header::read(view, offset)?.len as usize;
 
-        let len = PrefixType::from_le_bytes(buf) as usize;
         if len < 256 {
             let mut buf = [0; 256];
-            view.read(offset + size_of::<PrefixType>() as u64, &mut buf[..len])?;
-            Ok((bincode::decode_from_slice(&buf[..len], standard())?.0, len))
+            view.read(offset + HEADER_SIZE as u64, &mut buf[..len])?;
+            Ok((C::decode_from_slice(&buf[..len])?, len))
         } else {
-            let mut buf = Vec::with_capacity(len);
-            unsafe {
-                buf.set_len(len);
-            }
-
-            view.read(offset + size_of::<PrefixType>() as u64, &mut buf[..])?;
-            Ok((
-                bincode::decode_from_slice(&buf[..], standard())?.0,
-                buf.len(),
-            ))
+            let mut buf = vec![0; len];
+            view.read(offset + HEADER_SIZE as u64, &mut buf[..])?;
+            Ok((C::decode_from_slice(&buf[..])?, buf.len()))
         }
     }
 
@@ -50,24 +44,87 @@ impl<T: Encode + Decode, M: MemorySize> ManagedPtr<T, M> {
         store: &mut impl AsStoreMut,
         instance: &Instance,
     ) -> Result<(), ScotchHostError> {
-        let func = instance
+        free_in(self.offset.into(), len + HEADER_SIZE, store, instance)
+    }
+}
+
+unsafe impl<T, M: MemorySize, C> FromToNativeWasmType for ManagedPtr<T, M, C>
+where
+    M::Native: NativeWasmTypeInto,
+{
+    type Native = M::Native;
+
+    #[inline]
+    fn from_native(native: Self::Native) -> Self {
+        Self {
+            offset: M::native_to_offset(native),
+            _ty: PhantomData,
+        }
+    }
+
+    #[inline]
+    fn to_native(self) -> Self::Native {
+        M::offset_to_native(self.offset)
+    }
+}
+
+/// Pointer to a cell with the address of an envelope created by the guest to pass `&mut T`.
+/// Host writes the modified value back by replacing the envelope in the cell.
+#[doc(hidden)]
+pub struct ManagedMutPtr<T, M: MemorySize = GuestMemory, C = Bincode> {
+    offset: M::Offset,
+    _ty: PhantomData<fn() -> (T, C)>,
+}
+
+impl<T, M: MemorySize, C: Encodes<T>> ManagedMutPtr<T, M, C> {
+    fn envelope(&self, view: &MemoryView) -> Result<ManagedPtr<T, M, C>, ScotchHostError> {
+        let mut buf = [0; CELL_SIZE];
+        view.read(self.offset.into(), &mut buf)?;
+
+        let offset = u64::from_le_bytes(buf)
+            .try_into()
+            .map_err(|_| MemoryAccessError::Overflow)?;
+        Ok(ManagedPtr::new(offset))
+    }
+
+    pub fn read(&self, view: &MemoryView) -> Result<(T, usize), ScotchHostError> {
+        self.envelope(view)?.read(view)
+    }
+
+    /// Replaces the envelope in the cell with encoded `value` and frees the old one.
+    /// `len` is the length returned by [`ManagedMutPtr::read`].
+    pub fn write_back_in(
+        &self,
+        value: &T,
+        len: usize,
+        store: &mut impl AsStoreMut,
+        instance: &Instance,
+    ) -> Result<(), ScotchHostError> {
+        let old = {
+            let view = instance
+                .exports
+                .get_memory("memory")
+                .map_err(ScotchHostError::MemoryMissing)?
+                .view(store);
+            self.envelope(&view)?
+        };
+
+        let new: u64 = EncodedPtr::<T, M, C>::new_in(value, store, instance)?
+            .to_managed()
+            .offset
+            .into();
+        instance
             .exports
-            .get_function("__scotch_free")
-            .map_err(ScotchHostError::FreeMissing)?;
-        func.call(
-            store,
-            &[
-                (self.offset.into() as i32).into(),
-                ((len + size_of::<PrefixType>()) as i32).into(),
-                1i32.into(),
-            ],
-        )
-        .map(|_| ())
-        .map_err(ScotchHostError::FreeFailed)
+            .get_memory("memory")
+            .map_err(ScotchHostError::MemoryMissing)?
+            .view(store)
+            .write(self.offset.into(), &new.to_le_bytes())?;
+
+        old.free_in(len, store, instance)
     }
 }
 
-unsafe impl<T: Encode + Decode, M: MemorySize> FromToNativeWasmType for ManagedPtr<T, M>
+unsafe impl<T, M: MemorySize, C> FromToNativeWasmType for ManagedMutPtr<T, M, C>
 where
     M::Native: NativeWasmTypeInto,
 {
@@ -0,0 +1,219 @@
+use crate::{compiler::EngineConfig, GuestFunctionHandle, WasmPlugin, WasmPluginBuilder};
+use std::{any::Any, collections::BTreeMap, fmt};
+use wasmer::Engine;
+
+#[cfg(feature = "compiler")]
+use crate::ScotchHostError;
+#[cfg(feature = "compiler")]
+use std::path::{Path, PathBuf};
+
+/// Identifier of a plugin in [`PluginManager`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PluginId(u32);
+
+impl fmt::Display for PluginId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Path of a file loaded by [`PluginManager::load_dir`] and the result of loading it.
+#[cfg(feature = "compiler")]
+#[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "compiler")))]
+pub type LoadedFile = (PathBuf, Result<PluginId, ScotchHostError>);
+
+struct ManagedPlugin {
+    name: String,
+    plugin: WasmPlugin,
+}
+
+/// Collection of plugins that share one [`Engine`].
+/// ```ignore
+/// let mut manager = PluginManager::new();
+/// let loaded = manager.load_dir("plugins", |_name, builder| {
+///     builder
+///         .with_state(())
+///         .with_imports(make_imports![print])
+///         .with_exports(make_exports![add_up_list])
+/// })?;
+///
+/// for (id, out) in manager.broadcast::<add_up_list, _>(|f| f(&vec![1, 2, 3])) {
+///     println!("{}: {out:?}", manager.name(id).unwrap());
+/// }
+/// ```
+pub struct PluginManager {
+    engine: Engine,
+    plugins: BTreeMap<PluginId, ManagedPlugin>,
+    next_id: u32,
+}
+
+impl PluginManager {
+    /// Creates new [`PluginManager`] with the default engine.
+    pub fn new() -> Self {
+        Self::new_with_engine(EngineConfig::default().engine())
+    }
+
+    /// Creates new [`PluginManager`] that compiles plugins with `engine`.
+    pub fn new_with_engine(engine: impl Into<Engine>) -> Self {
+        Self {
+            engine: engine.into(),
+            plugins: BTreeMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Engine shared by the plugins.
+    #[inline]
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    /// Creates a builder that uses the shared engine, plugin can be added with [`Self::insert`].
+    pub fn builder<S: Any + Send + Sized + 'static>(&self) -> WasmPluginBuilder<S> {
+        WasmPluginBuilder::new_with_engine(self.engine.clone())
+    }
+
+    /// Adds a plugin to the manager.
+    pub fn insert(&mut self, name: impl Into<String>, plugin: WasmPlugin) -> PluginId {
+        let id = PluginId(self.next_id);
+        self.next_id += 1;
+
+        self.plugins.insert(
+            id,
+            ManagedPlugin {
+                name: name.into(),
+                plugin,
+            },
+        );
+        id
+    }
+
+    /// Compiles and adds a plugin, `setup` has to set the state, imports and exports.
+    #[cfg(feature = "compiler")]
+    #[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "compiler")))]
+    pub fn load<S: Any + Send + Sized + 'static>(
+        &mut self,
+        name: impl Into<String>,
+        bytecode: &[u8],
+        setup: impl FnOnce(WasmPluginBuilder<S>) -> WasmPluginBuilder<S>,
+    ) -> Result<PluginId, ScotchHostError> {
+        let plugin = setup(self.builder()).from_binary(bytecode)?.finish()?;
+        Ok(self.insert(name, plugin))
+    }
+
+    /// Compiles and adds a plugin from file, file name without the extension becomes the name.
+    #[cfg(feature = "compiler")]
+    #[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "compiler")))]
+    pub fn load_file<S: Any + Send + Sized + 'static>(
+        &mut self,
+        path: impl AsRef<Path>,
+        setup: impl FnOnce(&str, WasmPluginBuilder<S>) -> WasmPluginBuilder<S>,
+    ) -> Result<PluginId, ScotchHostError> {
+        let path = path.as_ref();
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let bytecode = std::fs::read(path)?;
+
+        self.load(name.clone(), &bytecode, |builder| setup(&name, builder))
+    }
+
+    /// Compiles and adds every `.wasm` file in `dir` in alphabetical order.
+    /// A plugin that fails to load does not stop the rest, result of every file is returned.
+    /// ```ignore
+    /// for (path, result) in manager.load_dir("plugins", setup)? {
+    ///     if let Err(e) = result {
+    ///         eprintln!("{}: {e}", path.display());
+    ///     }
+    /// }
+    /// ```
+    #[cfg(feature = "compiler")]
+    #[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "compiler")))]
+    pub fn load_dir<S: Any + Send + Sized + 'static>(
+        &mut self,
+        dir: impl AsRef<Path>,
+        mut setup: impl FnMut(&str, WasmPluginBuilder<S>) -> WasmPluginBuilder<S>,
+    ) -> Result<Vec<LoadedFile>, ScotchHostError> {
+        let mut paths = std::fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        paths.retain(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "wasm"));
+        paths.sort();
+
+        Ok(paths
+            .into_iter()
+            .map(|path| {
+                let result = self.load_file(&path, &mut setup);
+                (path, result)
+            })
+            .collect())
+    }
+
+    /// Removes a plugin from the manager.
+    pub fn remove(&mut self, id: PluginId) -> Option<WasmPlugin> {
+        self.plugins.remove(&id).map(|entry| entry.plugin)
+    }
+
+    #[inline]
+    pub fn get(&self, id: PluginId) -> Option<&WasmPlugin> {
+        self.plugins.get(&id).map(|entry| &entry.plugin)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, id: PluginId) -> Option<&mut WasmPlugin> {
+        self.plugins.get_mut(&id).map(|entry| &mut entry.plugin)
+    }
+
+    /// Name the plugin was added with.
+    #[inline]
+    pub fn name(&self, id: PluginId) -> Option<&str> {
+        self.plugins.get(&id).map(|entry| &entry.name[..])
+    }
+
+    /// Looks up a plugin by name. If names are not unique returns the oldest plugin.
+    pub fn find(&self, name: &str) -> Option<PluginId> {
+        self.plugins
+            .iter()
+            .find(|(_, entry)| entry.name == name)
+            .map(|(id, _)| *id)
+    }
+
+    /// Iterates over plugins in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = (PluginId, &WasmPlugin)> {
+        self.plugins.iter().map(|(id, entry)| (*id, &entry.plugin))
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.plugins.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    /// Calls `call` with the guest function of every plugin that exports it,
+    /// plugins that do not export the function are skipped.
+    /// ```ignore
+    /// let sums: Vec<(PluginId, Result<i32, RuntimeError>)> =
+    ///     manager.broadcast::<add_up_list, _>(|f| f(&nums));
+    /// ```
+    pub fn broadcast<H: GuestFunctionHandle + 'static, R>(
+        &self,
+        mut call: impl FnMut(&H::Callback) -> R,
+    ) -> Vec<(PluginId, R)> {
+        self.plugins
+            .iter()
+            .filter_map(|(id, entry)| Some((*id, entry.plugin.with_function::<H, _>(&mut call)?)))
+            .collect()
+    }
+}
+
+impl Default for PluginManager {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{GuestUsize, ScotchHostError};
+use wasmer::{AsStoreMut, Instance, Value};
+
+/// Converts `value` to the guest `usize`, allocator functions take all arguments as it.
+fn guest_usize(value: u64) -> Value {
+    (value as GuestUsize).into()
+}
+
+/// Allocates `size` bytes in the guest memory with `__scotch_alloc`.
+pub(crate) fn alloc_in(
+    size: usize,
+    store: &mut impl AsStoreMut,
+    instance: &Instance,
+) -> Result<u64, ScotchHostError> {
+    let func = instance
+        .exports
+        .get_function("__scotch_alloc")
+        .map_err(ScotchHostError::AllocMissing)?;
+    let out = &func
+        .call(store, &[guest_usize(size as u64), guest_usize(1)])
+        .map_err(ScotchHostError::AllocFailed)?[0];
+
+    #[cfg(feature = "mem64")]
+    let ptr = out.unwrap_i64() as u64;
+    #[cfg(not(feature = "mem64"))]
+    let ptr = out.unwrap_i32() as u32 as u64;
+
+    // Guest allocator returns null if it failed to grow the memory.
+    if ptr == 0 {
+        return Err(ScotchHostError::MemoryLimitExceeded);
+    }
+
+    Ok(ptr)
+}
+
+/// Frees `size` bytes at `offset` in the guest memory with `__scotch_free`.
+pub(crate) fn free_in(
+    offset: u64,
+    size: usize,
+    store: &mut impl AsStoreMut,
+    instance: &Instance,
+) -> Result<(), ScotchHostError> {
+    let func = instance
+        .exports
+        .get_function("__scotch_free")
+        .map_err(ScotchHostError::FreeMissing)?;
+    func.call(
+        store,
+        &[
+            guest_usize(offset),
+            guest_usize(size as u64),
+            guest_usize(1),
+        ],
+    )
+    .map(|_| ())
+    .map_err(ScotchHostError::FreeFailed)
+}
@@ -1,30 +1,99 @@
-use crate::{CallbackRef, GuestFunctionCreator, GuestFunctionHandle, InstanceRef, StoreRef};
+use crate::{
+    codec::check_codec,
+    compiler::EngineConfig,
+    interrupt,
+    store::{self, CallLimits},
+    tunables::LimitingTunables,
+    Bincode, CallbackRef, Codec, CodecId, GuestFunctionCreator, GuestFunctionHandle, InstanceRef,
+    PluginStore, RunningCall, ScotchHostError, StoreRef,
+};
+use parking_lot::{MappedMutexGuard, MutexGuard};
 use std::{
     any::{Any, TypeId},
     collections::{hash_map::Entry, HashMap},
     path::Path,
-    sync::{Arc, Weak},
+    sync::{atomic::AtomicBool, Arc, Weak},
+    time::Duration,
 };
 use wasmer::{
-    CompileError, DeserializeError, Extern, FunctionEnv, Imports, Instance, InstantiationError,
-    Module, SerializeError, Store,
+    BaseTunables, DeserializeError, Engine, Extern, FunctionEnv, Imports, Instance, Module, Pages,
+    SerializeError, Store, Target,
+};
+use wasmer_middlewares::metering::MeteringPoints;
+
+#[cfg(feature = "compiler")]
+use wasmer::CompileError;
+
+#[cfg(feature = "cache")]
+use crate::ModuleCache;
+#[cfg(feature = "wasi")]
+use crate::{
+    wasi::{self, WasiOutput},
+    WasiConfig,
 };
+#[cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass"))]
+use crate::{Compiler, OptLevel};
+#[cfg(feature = "signing")]
+use crate::{PluginSignature, TrustStore};
 
 #[doc(hidden)]
 pub struct WasmEnv<S: Any + Send + Sized + 'static> {
     pub instance: Weak<Instance>,
-    pub state: S,
+    /// `None` only while the state is moved to a reloaded plugin.
+    state: Option<S>,
+    /// Call that is running, host functions trap if it was interrupted.
+    pub running: Arc<RunningCall>,
+}
+
+impl<S: Any + Send + Sized + 'static> WasmEnv<S> {
+    /// State passed to [`WasmPluginBuilder::with_state`].
+    #[inline]
+    pub fn state_mut(&mut self) -> &mut S {
+        self.state
+            .as_mut()
+            .expect("State was moved out of the plugin")
+    }
+
+    #[inline]
+    pub(crate) fn take_state(&mut self) -> Option<S> {
+        self.state.take()
+    }
+
+    #[cfg(feature = "compiler")]
+    #[inline]
+    pub(crate) fn set_state(&mut self, state: S) {
+        self.state = Some(state);
+    }
 }
 
+/// Handle to the environment of host functions that lives in the store.
+struct EnvHandle<S: Any + Send + Sized + 'static>(FunctionEnv<WasmEnv<S>>);
+
+// SAFETY: Handle is only an index into the store, it is dereferenced only while the store is locked.
+unsafe impl<S: Any + Send + Sized + 'static> Send for EnvHandle<S> {}
+unsafe impl<S: Any + Send + Sized + 'static> Sync for EnvHandle<S> {}
+
 /// An instantiated plugin with cached exports.
+///
+/// Plugin is `Send + Sync` and can be shared between threads with `Arc`.
+/// Calls and state accessors lock the store of the plugin, so concurrent calls are serialized.
 #[allow(dead_code)]
 pub struct WasmPlugin {
     exports: HashMap<TypeId, CallbackRef>,
     store: StoreRef,
     module: Module,
     instance: InstanceRef,
+    /// `EnvHandle<S>` created by `with_state`.
+    env: Option<Box<dyn Any + Send + Sync>>,
+    #[cfg(feature = "wasi")]
+    wasi_output: WasiOutput,
 }
 
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<WasmPlugin>();
+};
+
 impl WasmPlugin {
     /// Creates a builder to create a new WasmPlugin.
     pub fn builder<E: Any + Send + Sized + 'static>() -> WasmPluginBuilder<E> {
@@ -53,6 +122,20 @@ impl WasmPlugin {
         self.exports.get(&type_id).and_then(|f| f.downcast_ref())
     }
 
+    /// Calls `f` with the guest export, resolving it from the wasm instance
+    /// without caching if it was not cached. Returns `None` if the export is missing.
+    pub fn with_function<H: GuestFunctionHandle + 'static, R>(
+        &self,
+        f: impl FnOnce(&H::Callback) -> R,
+    ) -> Option<R> {
+        if let Some(callback) = self.function::<H>() {
+            return Some(f(callback));
+        }
+
+        let (_, callback) = H::new().create(self.store.clone(), self.instance.clone())?;
+        callback.downcast_ref().map(f)
+    }
+
     /// Looks up cached guest export by function handle.
     /// # Panics
     /// If function was not cached with `make_exports!`.
@@ -85,6 +168,98 @@ impl WasmPlugin {
             .unwrap()
     }
 
+    /// Returns the amount of fuel left, `Some(0)` if it was exhausted.
+    /// Returns `None` if the plugin was not compiled with fuel metering,
+    /// see [`WasmPluginBuilder::with_fuel`].
+    pub fn fuel(&self) -> Option<u64> {
+        match store::remaining_fuel(&mut *self.store.lock(), &self.instance)? {
+            MeteringPoints::Remaining(fuel) => Some(fuel),
+            MeteringPoints::Exhausted => Some(0),
+        }
+    }
+
+    /// Sets the amount of fuel left. With [`WasmPluginBuilder::with_fuel_per_call`]
+    /// fuel is reset before every call anyway.
+    /// Fails with [`ScotchHostError::MeteringMissing`] if the plugin was not compiled
+    /// with fuel metering, see [`WasmPluginBuilder::with_fuel`].
+    pub fn set_fuel(&self, fuel: u64) -> Result<(), ScotchHostError> {
+        store::refuel(&mut *self.store.lock(), &self.instance, fuel)
+    }
+
+    /// Changes the timeout of guest calls, `None` disables it.
+    /// See [`WasmPluginBuilder::with_timeout`], calls fail with [`ScotchHostError::InterruptMissing`]
+    /// if the plugin was not compiled with interrupt checks.
+    pub fn set_timeout(&self, timeout: Option<Duration>) {
+        self.store.set_timeout(timeout);
+    }
+
+    /// Calls made by the current thread inside of `f` use `timeout` instead of the plugin timeout.
+    /// ```ignore
+    /// let out = plugin.with_call_timeout(Duration::from_millis(10), || {
+    ///     plugin.function_unwrap::<add_up_list>()(&nums)
+    /// });
+    /// ```
+    pub fn with_call_timeout<R>(&self, timeout: Duration, f: impl FnOnce() -> R) -> R {
+        PluginStore::with_call_timeout(timeout, f)
+    }
+
+    /// Calls `f` with the environment of host functions,
+    /// returns `None` if the state is not of type `S`.
+    pub(crate) fn with_env<S: Any + Send + Sized + 'static, R>(
+        &self,
+        f: impl FnOnce(&mut WasmEnv<S>) -> R,
+    ) -> Option<R> {
+        let env = self.env::<S>()?;
+        Some(f(env.as_mut(&mut *self.store.lock())))
+    }
+
+    /// Returns the state passed to [`WasmPluginBuilder::with_state`],
+    /// or `None` if the plugin has no state or it is not of type `S`.
+    ///
+    /// The guard allows to modify the state. It locks the plugin,
+    /// calling guest functions while holding it deadlocks.
+    /// ```ignore
+    /// let calls = plugin.state::<Counter>().unwrap().calls;
+    /// ```
+    pub fn state<S: Any + Send + Sized + 'static>(&self) -> Option<MappedMutexGuard<'_, S>> {
+        let env = self.env()?;
+        Some(MutexGuard::map(self.store.lock(), |store| {
+            env.as_mut(store).state_mut()
+        }))
+    }
+
+    /// Calls `f` with the state passed to [`WasmPluginBuilder::with_state`],
+    /// returns `None` if the plugin has no state or it is not of type `S`.
+    pub fn with_state<S: Any + Send + Sized + 'static, R>(
+        &self,
+        f: impl FnOnce(&mut S) -> R,
+    ) -> Option<R> {
+        self.with_env(|env| f(env.state_mut()))
+    }
+
+    fn env<S: Any + Send + Sized + 'static>(&self) -> Option<&FunctionEnv<WasmEnv<S>>> {
+        self.env
+            .as_ref()?
+            .downcast_ref::<EnvHandle<S>>()
+            .map(|handle| &handle.0)
+    }
+
+    /// Takes everything the plugin has written to stdout since the last call,
+    /// returns `None` if stdout was not captured with [`WasiConfig::capture_stdout`].
+    #[cfg(feature = "wasi")]
+    #[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "wasi")))]
+    pub fn take_stdout(&self) -> Option<Vec<u8>> {
+        self.wasi_output.take_stdout()
+    }
+
+    /// Takes everything the plugin has written to stderr since the last call,
+    /// returns `None` if stderr was not captured with [`WasiConfig::capture_stderr`].
+    #[cfg(feature = "wasi")]
+    #[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "wasi")))]
+    pub fn take_stderr(&self) -> Option<Vec<u8>> {
+        self.wasi_output.take_stderr()
+    }
+
     /// Serializes plugin into bytes to use with headless mode.
     pub fn serialize(&self) -> Result<Vec<u8>, SerializeError> {
         self.module.serialize().map(|bytes| bytes.to_vec())
@@ -125,6 +300,29 @@ pub struct WasmPluginBuilder<E: Any + Send + Sized + 'static> {
     imports: Option<Imports>,
     exports: Vec<Box<dyn GuestFunctionCreator>>,
     func_env: Option<FunctionEnv<WasmEnv<E>>>,
+    codec: CodecId,
+    limits: CallLimits,
+    engine_config: EngineConfig,
+    initial_memory: Option<u32>,
+    timeout: Option<Duration>,
+    running: Arc<RunningCall>,
+    grow_denied: Arc<AtomicBool>,
+    shared_engine: Option<Engine>,
+    /// Engine of a [`BuilderTemplate`], reused while the compiler settings match it.
+    reused_engine: Option<(EngineConfig, Engine)>,
+    /// Module of a [`BuilderTemplate`], used by `finish` if no other module was set.
+    template_module: Option<Module>,
+    #[cfg(feature = "wasi")]
+    wasi: Option<WasiConfig>,
+    #[cfg(feature = "cache")]
+    cache: Option<ModuleCache>,
+    /// Store was passed to `new_with_store`, so the compiler settings are unknown.
+    custom_store: bool,
+    #[cfg(feature = "signing")]
+    trust_store: Option<TrustStore>,
+    /// Module was passed to `from_module`, so its bytecode can not be checked by the trust store.
+    #[cfg(feature = "signing")]
+    precompiled: bool,
 }
 
 impl<S: Any + Send + Sized + 'static> WasmPluginBuilder<S> {
@@ -132,11 +330,30 @@ impl<S: Any + Send + Sized + 'static> WasmPluginBuilder<S> {
     #[inline]
     pub fn new() -> Self {
         Self {
-            store: Store::default(),
+            store: Store::new(EngineConfig::default().engine()),
             module: None,
             imports: None,
             func_env: None,
             exports: vec![],
+            codec: Bincode::ID,
+            limits: CallLimits::default(),
+            engine_config: EngineConfig::default(),
+            initial_memory: None,
+            timeout: None,
+            running: Arc::default(),
+            grow_denied: Arc::default(),
+            shared_engine: None,
+            reused_engine: None,
+            template_module: None,
+            #[cfg(feature = "wasi")]
+            wasi: None,
+            #[cfg(feature = "cache")]
+            cache: None,
+            custom_store: false,
+            #[cfg(feature = "signing")]
+            trust_store: None,
+            #[cfg(feature = "signing")]
+            precompiled: false,
         }
     }
 
@@ -144,19 +361,273 @@ impl<S: Any + Send + Sized + 'static> WasmPluginBuilder<S> {
     pub fn new_with_store(store: Store) -> Self {
         Self {
             store,
+            custom_store: true,
             ..Self::new()
         }
     }
 
-    /// Compiles bytecode with selected compiler. To change the compile use feature flags.
+    /// Creates new [`WasmPluginBuilder`] that compiles the plugin with `engine`,
+    /// so compiled code and compiler settings can be shared by many plugins.
+    /// Engine is kept when memory limits replace the store, but fuel metering can not be used.
+    pub fn new_with_engine(engine: impl Into<Engine>) -> Self {
+        let engine = engine.into();
+        Self {
+            store: Store::new(engine.clone()),
+            shared_engine: Some(engine),
+            ..Self::new()
+        }
+    }
+
+    /// Enables fuel metering, every executed instruction consumes one unit of fuel.
+    /// Plugin starts with `fuel` units that are shared by all calls,
+    /// use [`WasmPlugin::set_fuel`] to refill it.
+    /// Calls that run out of fuel fail with [`ScotchHostError::FuelExhausted`].
+    ///
+    /// Replaces the store, so it has to be called before `with_state` and `from_binary`.
+    #[cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass"))]
+    #[cfg_attr(
+        feature = "unstable-doc-cfg",
+        doc(cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass")))
+    )]
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        assert!(
+            self.shared_engine.is_none(),
+            "Fuel metering can not be used with a shared engine"
+        );
+
+        self.engine_config.fuel = Some(fuel);
+        self.rebuild_store();
+        self
+    }
+
+    /// Same as [`Self::with_fuel`] but every call gets `fuel` units of fuel,
+    /// whatever was left after the previous call is discarded.
+    #[cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass"))]
+    #[cfg_attr(
+        feature = "unstable-doc-cfg",
+        doc(cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass")))
+    )]
+    pub fn with_fuel_per_call(self, fuel: u64) -> Self {
+        let mut this = self.with_fuel(fuel);
+        this.limits.fuel_per_call = Some(fuel);
+        this
+    }
+
+    /// Selects the compiler of the plugin, every compiler enabled with feature flags can be used.
+    ///
+    /// Replaces the store, so it has to be called before `with_state` and `from_binary`.
+    #[cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass"))]
+    #[cfg_attr(
+        feature = "unstable-doc-cfg",
+        doc(cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass")))
+    )]
+    pub fn with_compiler(mut self, compiler: Compiler) -> Self {
+        assert!(
+            self.shared_engine.is_none(),
+            "Compiler of a shared engine can not be changed"
+        );
+
+        self.engine_config.compiler = compiler;
+        self.rebuild_store();
+        self
+    }
+
+    /// Sets optimization level of the compiler, default is [`OptLevel::Speed`].
+    ///
+    /// Replaces the store, so it has to be called before `with_state` and `from_binary`.
+    #[cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass"))]
+    #[cfg_attr(
+        feature = "unstable-doc-cfg",
+        doc(cfg(any(feature = "cranelift", feature = "llvm", feature = "singlepass")))
+    )]
+    pub fn with_opt_level(mut self, opt_level: OptLevel) -> Self {
+        assert!(
+            self.shared_engine.is_none(),
+            "Compiler of a shared engine can not be changed"
+        );
+
+        self.engine_config.opt_level = opt_level;
+        self.rebuild_store();
+        self
+    }
+
+    /// Limits plugin memory to `pages` wasm pages of 64 KiB.
+    /// Plugins that need more memory from the start fail to instantiate,
+    /// allocations above the limit fail with [`ScotchHostError::MemoryLimitExceeded`].
+    ///
+    /// Replaces the store, so it has to be called before `with_state` and `from_binary`.
+    pub fn with_memory_limit(mut self, pages: u32) -> Self {
+        self.limits.memory_limit = Some(pages);
+        self.rebuild_store();
+        self
+    }
+
+    /// Grows plugin memory to `pages` wasm pages of 64 KiB when the plugin is instantiated,
+    /// so the plugin does not have to grow it on first allocations.
+    pub fn with_initial_memory(mut self, pages: u32) -> Self {
+        self.initial_memory = Some(pages);
+        self
+    }
+
+    /// Guest calls that take longer than `timeout` fail with [`ScotchHostError::Timeout`].
+    /// Calls run on the calling thread and a watchdog thread marks them as timed out
+    /// when the timeout elapses, they trap at the next loop iteration, function call
+    /// or host function and the plugin can be called again.
+    ///
+    /// Guest code checks for interruptions only if it was compiled by an engine of scotch,
+    /// e.g. [`Compiler::engine`](crate::Compiler::engine). [`Self::finish`] fails with
+    /// [`ScotchHostError::InterruptMissing`] for other plugins.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Provides WASI to the plugin, which is required by plugins compiled for `wasm32-wasi`.
+    #[cfg(feature = "wasi")]
+    #[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "wasi")))]
+    pub fn with_wasi(mut self, config: WasiConfig) -> Self {
+        self.wasi = Some(config);
+        self
+    }
+
+    /// Loads compiled plugins from `cache` in [`Self::from_binary`] and stores newly compiled ones in it.
+    /// Plugins created with [`Self::new_with_engine`] or [`Self::new_with_store`] are always compiled,
+    /// because settings of their engine are unknown.
+    #[cfg(feature = "cache")]
+    #[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "cache")))]
+    pub fn with_cache(mut self, cache: ModuleCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Only plugins signed by a key from `trust` can be loaded with [`Self::from_binary`].
+    /// Plugins created with [`Self::from_module`] are rejected by [`Self::finish`], because their
+    /// bytecode can not be checked, use `new_signed` of [`PluginPool`](crate::PluginPool)
+    /// and [`PluginTemplate`](crate::PluginTemplate) instead.
+    /// Serialized plugins are not checked, use `from_verified_serialized` of `verify` feature for them.
+    #[cfg(feature = "signing")]
+    #[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "signing")))]
+    pub fn with_trust_store(mut self, trust: TrustStore) -> Self {
+        self.trust_store = Some(trust);
+        self
+    }
+
+    /// Recreates the store so it uses current compiler, fuel and memory settings.
+    fn rebuild_store(&mut self) {
+        // Module is compiled by the engine, a shared engine stays the same.
+        assert!(
+            (self.module.is_none() || self.shared_engine.is_some()) && self.func_env.is_none(),
+            "Compiler, fuel and memory limits must be set before `with_state` and `from_binary`"
+        );
+
+        let engine = match (&self.shared_engine, &self.reused_engine) {
+            (Some(engine), _) => engine.clone(),
+            (None, Some((config, engine))) if *config == self.engine_config => engine.clone(),
+            _ => self.engine_config.engine(),
+        };
+        self.store = match self.limits.memory_limit {
+            Some(limit) => Store::new_with_tunables(
+                engine,
+                LimitingTunables::new(
+                    BaseTunables::for_target(&Target::default()),
+                    Pages(limit),
+                    self.grow_denied.clone(),
+                ),
+            ),
+            None => Store::new(engine),
+        };
+        self.custom_store = false;
+    }
+
+    /// Compiles bytecode with selected compiler, see [`Self::with_compiler`].
     /// Default compiler is `cranelift`.
+    /// With a trust store set by `with_trust_store` the plugin has to be signed with `sign_plugin`,
+    /// otherwise it is rejected with `ScotchHostError::SignatureRejected` before compilation.
     #[cfg(feature = "compiler")]
     #[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "compiler")))]
-    pub fn from_binary(mut self, bytecode: &[u8]) -> Result<Self, CompileError> {
-        self.module = Some(Module::from_binary(&self.store, bytecode)?);
+    pub fn from_binary(mut self, bytecode: &[u8]) -> Result<Self, ScotchHostError> {
+        self.verify_and_compile(bytecode)?;
         Ok(self)
     }
 
+    /// Same as [`Self::from_binary`] but checks `signature` distributed separately from the plugin.
+    /// # Panics
+    /// If trust store was not set with [`Self::with_trust_store`].
+    #[cfg(feature = "signing")]
+    #[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "signing")))]
+    pub fn from_binary_with_signature(
+        mut self,
+        bytecode: &[u8],
+        signature: &PluginSignature,
+    ) -> Result<Self, ScotchHostError> {
+        self.trust_store
+            .as_ref()
+            .expect("You need to call `with_trust_store` first")
+            .verify_detached(bytecode, signature)?;
+
+        self.compile(bytecode)?;
+        Ok(self)
+    }
+
+    /// Same as [`Self::from_binary`] followed by [`Self::finish`],
+    /// but gives the state back if the plugin fails to load.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn load(
+        mut self,
+        bytecode: &[u8],
+    ) -> Result<WasmPlugin, (ScotchHostError, Option<S>)> {
+        match self.verify_and_compile(bytecode) {
+            Ok(()) => self.try_finish(),
+            Err(e) => Err((e, self.take_state())),
+        }
+    }
+
+    #[cfg(feature = "compiler")]
+    fn verify_and_compile(&mut self, bytecode: &[u8]) -> Result<(), ScotchHostError> {
+        #[cfg(feature = "signing")]
+        if let Some(trust) = &self.trust_store {
+            trust.verify(bytecode)?;
+        }
+
+        Ok(self.compile(bytecode)?)
+    }
+
+    #[cfg(feature = "compiler")]
+    fn compile(&mut self, bytecode: &[u8]) -> Result<(), CompileError> {
+        #[cfg(feature = "cache")]
+        if let Some(cache) = &self.cache {
+            if self.shared_engine.is_none() && !self.custom_store {
+                let key = ModuleCache::key(bytecode, &self.engine_config, self.limits.memory_limit);
+                let module = match cache.load(&self.store, &key) {
+                    Some(module) => module,
+                    None => {
+                        let module = Module::from_binary(&self.store, bytecode)?;
+                        // Failing to write the cache only makes the next launch slower.
+                        let _ = cache.store(&key, &module);
+                        module
+                    }
+                };
+
+                self.module = Some(module);
+                return Ok(());
+            }
+        }
+
+        self.module = Some(Module::from_binary(&self.store, bytecode)?);
+        Ok(())
+    }
+
+    /// Creates plugin from already compiled module, which is cheap to clone.
+    /// Module has to be compiled by the same engine, see [`Self::new_with_engine`].
+    pub fn from_module(mut self, module: Module) -> Self {
+        self.module = Some(module);
+        #[cfg(feature = "signing")]
+        {
+            self.precompiled = true;
+        }
+        self
+    }
+
     /// Creates plugin from bytes created by [`WasmPlugin::serialize`].
     /// # Safety
     /// See [`Module::deserialize`].
@@ -228,7 +699,8 @@ impl<S: Any + Send + Sized + 'static> WasmPluginBuilder<S> {
             &mut self.store,
             WasmEnv {
                 instance: Weak::new(),
-                state,
+                state: Some(state),
+                running: self.running.clone(),
             },
         ));
         self
@@ -259,26 +731,52 @@ impl<S: Any + Send + Sized + 'static> WasmPluginBuilder<S> {
         self
     }
 
-    /// Finishes building a `WasmPlugin`.
-    #[allow(clippy::result_large_err)]
-    pub fn finish(mut self) -> Result<WasmPlugin, InstantiationError> {
-        let module = self
-            .module
-            .expect("You need to call `from_binary` or `from_serialized` first");
-        let instance: InstanceRef =
-            Instance::new(&mut self.store, &module, &self.imports.unwrap_or_default())?.into();
-        instance
-            .exports
-            .get_memory("memory")
-            .unwrap()
-            .grow(&mut self.store, 3)
-            .unwrap();
+    /// Sets the codec the plugin was built with, [`Bincode`] by default.
+    /// Guest function handles passed to `with_exports` have to use the same codec.
+    pub fn with_codec<C: Codec>(mut self) -> Self {
+        self.codec = C::ID;
+        self
+    }
 
-        if let Some(env) = self.func_env.as_mut() {
-            env.as_mut(&mut self.store).instance = Arc::downgrade(&instance);
-        }
+    /// Finishes building a `WasmPlugin`.
+    /// Fails if the plugin or guest function handles use a different [`Codec`],
+    /// see [`Self::with_codec`].
+    pub fn finish(self) -> Result<WasmPlugin, ScotchHostError> {
+        self.try_finish().map_err(|(e, _)| e)
+    }
 
-        let store: StoreRef = Arc::new(self.store.into());
+    /// Same as [`Self::finish`] but gives the state back if the plugin fails to instantiate.
+    fn try_finish(mut self) -> Result<WasmPlugin, (ScotchHostError, Option<S>)> {
+        let module = match self.module.take() {
+            Some(module) => module,
+            None => {
+                let module = self
+                    .template_module
+                    .take()
+                    .expect("You need to call `from_binary` or `from_serialized` first");
+                // Module has to be instantiated in a store of the engine that compiled it.
+                assert!(
+                    self.shared_engine.is_some()
+                        || matches!(self.reused_engine, Some((config, _)) if config == self.engine_config),
+                    "Compiler and fuel settings must match the template"
+                );
+                module
+            }
+        };
+        let instantiated = match self.instantiate(&module) {
+            Ok(instantiated) => instantiated,
+            Err(e) => return Err((e, self.take_state())),
+        };
+        let instance = instantiated.instance;
+
+        let store: StoreRef = Arc::new(PluginStore::new(
+            self.store,
+            self.limits,
+            self.timeout,
+            instantiated.interruptible,
+            self.running,
+            self.grow_denied,
+        ));
         let exports = self
             .exports
             .into_iter()
@@ -290,8 +788,189 @@ impl<S: Any + Send + Sized + 'static> WasmPluginBuilder<S> {
             exports,
             instance,
             module,
+            env: self
+                .func_env
+                .map(|env| Box::new(EnvHandle(env)) as Box<dyn Any + Send + Sync>),
+            #[cfg(feature = "wasi")]
+            wasi_output: instantiated.wasi_output,
         })
     }
+
+    fn instantiate(&mut self, module: &Module) -> Result<Instantiated, ScotchHostError> {
+        #[cfg(feature = "signing")]
+        if self.precompiled && self.trust_store.is_some() {
+            return Err(crate::SignatureError::Precompiled.into());
+        }
+        let interruptible = interrupt::is_interruptible(module);
+        if self.timeout.is_some() && !interruptible {
+            return Err(ScotchHostError::InterruptMissing);
+        }
+
+        let mut imports = self.imports.take().unwrap_or_default();
+        if interruptible {
+            let env = FunctionEnv::new(&mut self.store, self.running.clone());
+            imports.define(
+                interrupt::IMPORT_MODULE,
+                interrupt::IMPORT_NAME,
+                Function::new_typed_with_env(&mut self.store, &env, check_interrupt),
+            );
+        }
+        #[cfg(feature = "wasi")]
+        let wasi = self
+            .wasi
+            .take()
+            .map(|config| config.build(&mut self.store, module, &mut imports))
+            .transpose()?;
+
+        let instance: InstanceRef = Instance::new(&mut self.store, module, &imports)?.into();
+        #[cfg(feature = "wasi")]
+        let wasi_output = match wasi {
+            Some((mut env, output)) => {
+                wasi::initialize(&mut env, &mut self.store, &instance)?;
+                output
+            }
+            None => WasiOutput::default(),
+        };
+        check_codec(&mut self.store, &instance, self.codec)?;
+        if let Some(export) = self.exports.iter().find(|ex| ex.codec() != self.codec) {
+            return Err(ScotchHostError::CodecMismatch {
+                expected: self.codec,
+                found: Some(export.codec()),
+            });
+        }
+        if let Some(pages) = self.initial_memory {
+            let memory = instance
+                .exports
+                .get_memory("memory")
+                .map_err(ScotchHostError::MemoryMissing)?;
+            let current = memory.view(&self.store).size();
+            if current < Pages(pages) {
+                memory
+                    .grow(&mut self.store, Pages(pages) - current)
+                    .map_err(|_| ScotchHostError::MemoryLimitExceeded)?;
+            }
+        }
+
+        if let Some(env) = self.func_env.as_mut() {
+            env.as_mut(&mut self.store).instance = Arc::downgrade(&instance);
+        }
+
+        Ok(Instantiated {
+            instance,
+            interruptible,
+            #[cfg(feature = "wasi")]
+            wasi_output,
+        })
+    }
+
+    /// Moves the state out of the builder, e.g. to give it back after a failed reload.
+    fn take_state(&mut self) -> Option<S> {
+        let env = self.func_env.as_ref()?;
+        env.as_mut(&mut self.store).take_state()
+    }
+}
+
+/// Called by guest code compiled with interrupt checks, traps if the running call was interrupted.
+fn check_interrupt(env: FunctionEnvMut<Arc<RunningCall>>) -> Result<(), ScotchHostError> {
+    env.data().check()
+}
+
+/// Instance created by [`WasmPluginBuilder::finish`] before it becomes a [`WasmPlugin`].
+struct Instantiated {
+    instance: InstanceRef,
+    interruptible: bool,
+    #[cfg(feature = "wasi")]
+    wasi_output: WasiOutput,
+}
+
+impl<S: Any + Send + Sized + 'static> WasmPluginBuilder<S> {
+    /// Takes the compiled module and the settings of the builder,
+    /// so more builders that share its engine can be created with [`Self::from_template`].
+    pub(crate) fn template(&self) -> BuilderTemplate {
+        BuilderTemplate {
+            engine_config: (self.shared_engine.is_none() && !self.custom_store)
+                .then_some(self.engine_config),
+            engine: self.store.engine().clone(),
+            module: self
+                .module
+                .clone()
+                .expect("You need to call `from_binary` first"),
+            codec: self.codec,
+            limits: self.limits,
+            initial_memory: self.initial_memory,
+            timeout: self.timeout,
+            #[cfg(feature = "wasi")]
+            wasi: self.wasi.clone(),
+        }
+    }
+
+    /// Creates builder with the settings of `template`, `finish` instantiates its module.
+    /// Settings can be changed, except for the compiler and fuel metering.
+    pub(crate) fn from_template(template: &BuilderTemplate) -> Self {
+        let mut this = match template.engine_config {
+            Some(config) => Self {
+                engine_config: config,
+                reused_engine: Some((config, template.engine.clone())),
+                ..Self::new()
+            },
+            None => Self::new_with_engine(template.engine.clone()),
+        };
+
+        this.codec = template.codec;
+        this.limits = template.limits;
+        this.initial_memory = template.initial_memory;
+        this.timeout = template.timeout;
+        #[cfg(feature = "wasi")]
+        {
+            this.wasi = template.wasi.clone();
+        }
+        this.rebuild_store();
+        this.template_module = Some(template.module.clone());
+        this
+    }
+}
+
+/// Compiled module and settings of a [`WasmPluginBuilder`] without its state,
+/// used by [`PluginPool`](crate::PluginPool) and [`PluginTemplate`](crate::PluginTemplate).
+#[derive(Clone)]
+pub(crate) struct BuilderTemplate {
+    /// `None` if the engine was passed by the user, its settings are unknown.
+    engine_config: Option<EngineConfig>,
+    engine: Engine,
+    module: Module,
+    codec: CodecId,
+    limits: CallLimits,
+    initial_memory: Option<u32>,
+    timeout: Option<Duration>,
+    #[cfg(feature = "wasi")]
+    wasi: Option<WasiConfig>,
+}
+
+impl BuilderTemplate {
+    /// Template of a module compiled by `engine` with default settings.
+    pub(crate) fn new(engine: Engine, module: Module) -> Self {
+        Self {
+            engine_config: None,
+            engine,
+            module,
+            codec: Bincode::ID,
+            limits: CallLimits::default(),
+            initial_memory: None,
+            timeout: None,
+            #[cfg(feature = "wasi")]
+            wasi: None,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    #[inline]
+    pub(crate) fn module(&self) -> &Module {
+        &self.module
+    }
 }
 
 impl<E: Any + Send + Sized + 'static> Default for WasmPluginBuilder<E> {
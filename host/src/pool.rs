@@ -0,0 +1,151 @@
+use crate::WasmPlugin;
+use parking_lot::{Condvar, Mutex};
+use std::ops::{Deref, DerefMut};
+
+#[cfg(feature = "signing")]
+use crate::TrustStore;
+#[cfg(feature = "compiler")]
+use crate::{ScotchHostError, WasmPluginBuilder};
+#[cfg(feature = "compiler")]
+use std::any::Any;
+
+/// Pool of instances of one plugin, so many threads can call the plugin at the same time.
+/// Every instance has its own store and state, calls check out a free instance
+/// and wait if all of them are busy.
+/// ```ignore
+/// let pool = PluginPool::new(8, PLUGIN_BYTES, |builder| {
+///     builder
+///         .with_state(0)
+///         .with_imports(make_imports![print])
+///         .with_exports(make_exports![add_up_list])
+/// })?;
+///
+/// let sum = pool.get().function_unwrap::<add_up_list>()(&nums)?;
+/// ```
+pub struct PluginPool {
+    free: Mutex<Vec<WasmPlugin>>,
+    released: Condvar,
+    size: usize,
+}
+
+impl PluginPool {
+    /// Compiles `bytecode` once and creates `size` instances from it.
+    /// `setup` is called for every instance and has to set the state, imports and exports.
+    /// Compiler, fuel and memory settings of the first instance are used to compile the plugin,
+    /// so `setup` has to set them the same way for every instance.
+    #[cfg(feature = "compiler")]
+    #[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "compiler")))]
+    pub fn new<S: Any + Send + Sized + 'static>(
+        size: usize,
+        bytecode: &[u8],
+        mut setup: impl FnMut(WasmPluginBuilder<S>) -> WasmPluginBuilder<S>,
+    ) -> Result<Self, ScotchHostError> {
+        let first = setup(WasmPluginBuilder::new()).from_binary(bytecode)?;
+        let template = first.template();
+
+        let mut plugins = Vec::with_capacity(size);
+        if size != 0 {
+            plugins.push(first.finish()?);
+        }
+        for _ in 1..size {
+            plugins.push(setup(WasmPluginBuilder::from_template(&template)).finish()?);
+        }
+        Ok(Self::from_plugins(plugins))
+    }
+
+    /// Same as [`Self::new`] but `bytecode` has to be signed by a key from `trust`,
+    /// otherwise it is rejected with [`ScotchHostError::SignatureRejected`] before compilation.
+    #[cfg(feature = "signing")]
+    #[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "signing")))]
+    pub fn new_signed<S: Any + Send + Sized + 'static>(
+        size: usize,
+        bytecode: &[u8],
+        trust: &TrustStore,
+        setup: impl FnMut(WasmPluginBuilder<S>) -> WasmPluginBuilder<S>,
+    ) -> Result<Self, ScotchHostError> {
+        trust.verify(bytecode)?;
+        Self::new(size, bytecode, setup)
+    }
+
+    /// Creates pool from instances that were created elsewhere.
+    /// They are expected to be instances of the same plugin.
+    pub fn from_plugins(plugins: Vec<WasmPlugin>) -> Self {
+        Self {
+            size: plugins.len(),
+            free: Mutex::new(plugins),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Checks out a free instance, waiting for one if all of them are busy.
+    /// Instance is returned to the pool when the guard is dropped.
+    /// # Panics
+    /// If the pool is empty.
+    pub fn get(&self) -> PooledPlugin<'_> {
+        assert!(self.size != 0, "Pool has no instances");
+
+        let mut free = self.free.lock();
+        loop {
+            if let Some(plugin) = free.pop() {
+                return PooledPlugin {
+                    pool: self,
+                    plugin: Some(plugin),
+                };
+            }
+
+            self.released.wait(&mut free);
+        }
+    }
+
+    /// Checks out a free instance, returns `None` if all of them are busy.
+    pub fn try_get(&self) -> Option<PooledPlugin<'_>> {
+        let plugin = self.free.lock().pop()?;
+        Some(PooledPlugin {
+            pool: self,
+            plugin: Some(plugin),
+        })
+    }
+
+    /// Amount of instances in the pool, including the ones that are checked out.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Amount of instances that are not checked out.
+    #[inline]
+    pub fn available(&self) -> usize {
+        self.free.lock().len()
+    }
+}
+
+/// Instance checked out from [`PluginPool`], returned to the pool on drop.
+pub struct PooledPlugin<'a> {
+    pool: &'a PluginPool,
+    plugin: Option<WasmPlugin>,
+}
+
+impl Deref for PooledPlugin<'_> {
+    type Target = WasmPlugin;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.plugin.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledPlugin<'_> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.plugin.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledPlugin<'_> {
+    fn drop(&mut self) {
+        if let Some(plugin) = self.plugin.take() {
+            self.pool.free.lock().push(plugin);
+            self.pool.released.notify_one();
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{ScotchHostError, WasmEnv, WasmPlugin, WasmPluginBuilder};
+use std::{
+    any::Any,
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+type Configure<S> = Box<dyn FnMut(S) -> WasmPluginBuilder<S>>;
+type Migrate<S> = Box<dyn FnMut(&mut S) -> S>;
+
+/// Plugin that is recompiled when its file changes on disk.
+///
+/// The file is not watched, it is checked when [`Self::reload_if_changed`] is polled,
+/// which compares the modification time of the file with the one of the last load.
+/// ```ignore
+/// let mut plugin = PollingHotPlugin::new("plugin.wasm", MyState::new(), |state| {
+///     WasmPlugin::builder()
+///         .with_state(state)
+///         .with_imports(make_imports![print])
+///         .with_exports(make_exports![add_up_list])
+/// })?;
+///
+/// loop {
+///     plugin.reload_if_changed()?;
+///     plugin.function_unwrap::<add_up_list>()(&nums)?;
+/// }
+/// ```
+#[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "compiler")))]
+pub struct PollingHotPlugin<S: Any + Send + Sized + 'static> {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    configure: Configure<S>,
+    migrate: Option<Migrate<S>>,
+    plugin: WasmPlugin,
+}
+
+impl<S: Any + Send + Sized + 'static> PollingHotPlugin<S> {
+    /// Compiles plugin from `path`. `configure` creates the builder with everything
+    /// but the bytecode and has to pass the state to `with_state`.
+    /// On every reload the state is moved out of the current plugin and `configure` is called
+    /// with it again, if the new plugin fails to load the state is moved back.
+    pub fn new(
+        path: impl AsRef<Path>,
+        state: S,
+        configure: impl FnMut(S) -> WasmPluginBuilder<S> + 'static,
+    ) -> Result<Self, ScotchHostError> {
+        Self::create(path, state, Box::new(configure), None)
+    }
+
+    /// Same as [`Self::new`] but `migrate` creates the state of the new plugin from the old one.
+    /// It is only called after the new plugin was instantiated, if reload fails
+    /// the old plugin and its state are left untouched.
+    pub fn new_with_migration(
+        path: impl AsRef<Path>,
+        state: S,
+        configure: impl FnMut(S) -> WasmPluginBuilder<S> + 'static,
+        migrate: impl FnMut(&mut S) -> S + 'static,
+    ) -> Result<Self, ScotchHostError> {
+        Self::create(path, state, Box::new(configure), Some(Box::new(migrate)))
+    }
+
+    fn create(
+        path: impl AsRef<Path>,
+        state: S,
+        mut configure: Configure<S>,
+        migrate: Option<Migrate<S>>,
+    ) -> Result<Self, ScotchHostError> {
+        let path = path.as_ref().to_path_buf();
+        let modified = modified_time(&path);
+        let bytecode = std::fs::read(&path)?;
+        let plugin = configure(state).load(&bytecode).map_err(|(e, _)| e)?;
+        plugin
+            .with_env(|_: &mut WasmEnv<S>| ())
+            .ok_or(ScotchHostError::StateMissing)?;
+
+        Ok(Self {
+            path,
+            modified,
+            configure,
+            migrate,
+            plugin,
+        })
+    }
+
+    /// Reloads the plugin if modification time of the file has changed since the last load.
+    /// Returns `true` if the plugin was reloaded.
+    pub fn reload_if_changed(&mut self) -> Result<bool, ScotchHostError> {
+        let modified = modified_time(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return Ok(false);
+        }
+
+        self.reload().map(|_| true)
+    }
+
+    /// Recompiles the plugin and swaps it with the current one.
+    /// Current plugin and its state are kept if the new one fails to load.
+    /// Fails with [`ScotchHostError::StateMissing`] if `configure` did not pass the state
+    /// to `with_state`, in which case the state is lost.
+    pub fn reload(&mut self) -> Result<(), ScotchHostError> {
+        let modified = modified_time(&self.path);
+        let bytecode = std::fs::read(&self.path)?;
+
+        let state = self
+            .plugin
+            .with_env(|env: &mut WasmEnv<S>| env.take_state())
+            .flatten()
+            .ok_or(ScotchHostError::StateMissing)?;
+        let plugin = match (self.configure)(state).load(&bytecode) {
+            Ok(plugin) => plugin,
+            Err((e, state)) => {
+                let state = state.ok_or(ScotchHostError::StateMissing)?;
+                self.plugin.with_env(|env| env.set_state(state));
+                return Err(e);
+            }
+        };
+
+        let migrate = &mut self.migrate;
+        plugin
+            .with_env(|env: &mut WasmEnv<S>| {
+                if let Some(migrate) = migrate {
+                    let state = migrate(env.state_mut());
+                    env.set_state(state);
+                }
+            })
+            .ok_or(ScotchHostError::StateMissing)?;
+
+        self.plugin = plugin;
+        self.modified = modified;
+        Ok(())
+    }
+
+    /// Path the plugin is loaded from.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Currently loaded plugin.
+    #[inline]
+    pub fn plugin(&self) -> &WasmPlugin {
+        &self.plugin
+    }
+}
+
+impl<S: Any + Send + Sized + 'static> Deref for PollingHotPlugin<S> {
+    type Target = WasmPlugin;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.plugin
+    }
+}
+
+impl<S: Any + Send + Sized + 'static> DerefMut for PollingHotPlugin<S> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.plugin
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
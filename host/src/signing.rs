@@ -0,0 +1,252 @@
+use ed25519_dalek::{Signature, Signer as _, SigningKey, VerifyingKey, PUBLIC_KEY_LENGTH};
+use sha2::{Digest, Sha256};
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+/// Name of the custom section that holds the signature, always the last section of the module.
+const SECTION_NAME: &str = "scotch.signature";
+/// Signed message is prefixed with it, so signatures can not be reused for other purposes.
+const CONTEXT: &[u8] = b"scotch plugin signature v1";
+const WASM_HEADER_LEN: usize = 8;
+
+/// Reason a plugin was rejected by [`TrustStore`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureError {
+    /// Plugin has no signature.
+    Unsigned,
+    /// Plugin is not valid wasm or its signature section is malformed or not the last section.
+    Malformed,
+    /// Plugin is signed by a key that is not in the trust store, contains bytes of the key.
+    UntrustedKey([u8; PUBLIC_KEY_LENGTH]),
+    /// Signature does not match the plugin, it was modified after signing.
+    InvalidSignature,
+    /// Plugin was created from an already compiled module, so its signature can not be checked.
+    Precompiled,
+}
+
+impl Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl Error for SignatureError {}
+
+/// Ed25519 signature of a plugin together with the public key that made it.
+/// Can be embedded into the plugin with [`sign_plugin`] or distributed next to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginSignature {
+    key: VerifyingKey,
+    signature: Signature,
+}
+
+impl PluginSignature {
+    /// Length of [`Self::to_bytes`].
+    pub const BYTE_SIZE: usize = PUBLIC_KEY_LENGTH + Signature::BYTE_SIZE;
+
+    /// Signs `bytecode` as is, for a signature that is distributed separately from the plugin.
+    pub fn sign(bytecode: &[u8], key: &SigningKey) -> Self {
+        Self {
+            key: key.verifying_key(),
+            signature: key.sign(&message(bytecode)),
+        }
+    }
+
+    /// Public key that made the signature.
+    #[inline]
+    pub fn key(&self) -> &VerifyingKey {
+        &self.key
+    }
+
+    /// Public key followed by the signature.
+    pub fn to_bytes(&self) -> [u8; Self::BYTE_SIZE] {
+        let mut bytes = [0; Self::BYTE_SIZE];
+        bytes[..PUBLIC_KEY_LENGTH].copy_from_slice(self.key.as_bytes());
+        bytes[PUBLIC_KEY_LENGTH..].copy_from_slice(&self.signature.to_bytes());
+        bytes
+    }
+
+    /// Parses bytes created by [`Self::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SignatureError> {
+        if bytes.len() != Self::BYTE_SIZE {
+            return Err(SignatureError::Malformed);
+        }
+
+        let (key, signature) = bytes.split_at(PUBLIC_KEY_LENGTH);
+        Ok(Self {
+            key: VerifyingKey::from_bytes(key.try_into().unwrap())
+                .map_err(|_| SignatureError::Malformed)?,
+            signature: Signature::from_slice(signature).map_err(|_| SignatureError::Malformed)?,
+        })
+    }
+
+    fn verify(&self, bytecode: &[u8]) -> Result<(), SignatureError> {
+        self.key
+            .verify_strict(&message(bytecode), &self.signature)
+            .map_err(|_| SignatureError::InvalidSignature)
+    }
+}
+
+/// Signs plugin with `key` and embeds the signature into it as a custom section,
+/// signature made by a previous call is replaced. Signed plugin is still a valid wasm module.
+/// ```ignore
+/// let signed = sign_plugin(&fs::read("plugin.wasm")?, &vendor_key)?;
+/// fs::write("plugin.wasm", signed)?;
+/// ```
+pub fn sign_plugin(bytecode: &[u8], key: &SigningKey) -> Result<Vec<u8>, SignatureError> {
+    let (unsigned, _) = split(bytecode)?;
+    let signature = PluginSignature::sign(unsigned, key).to_bytes();
+
+    let mut name = leb128(SECTION_NAME.len() as u32);
+    name.extend(SECTION_NAME.as_bytes());
+
+    let mut signed = unsigned.to_vec();
+    signed.push(0);
+    signed.extend(leb128((name.len() + signature.len()) as u32));
+    signed.extend(name);
+    signed.extend(signature);
+    Ok(signed)
+}
+
+/// Public keys of approved vendors, plugins signed by any of them are trusted.
+/// ```ignore
+/// let trust = TrustStore::new()
+///     .trust("acme", acme_key)
+///     .trust("initech", initech_key);
+///
+/// let plugin = WasmPlugin::builder()
+///     .with_trust_store(trust)
+///     .with_state(())
+///     .from_binary(PLUGIN_BYTES)?
+///     .finish()?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TrustStore {
+    keys: Vec<(String, VerifyingKey)>,
+}
+
+impl TrustStore {
+    /// Creates store that trusts nobody.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trusts plugins signed by `key` of `vendor`.
+    pub fn trust(mut self, vendor: impl Into<String>, key: VerifyingKey) -> Self {
+        self.keys.push((vendor.into(), key));
+        self
+    }
+
+    /// Stops trusting `key`, returns `true` if it was trusted.
+    pub fn revoke(&mut self, key: &VerifyingKey) -> bool {
+        let len = self.keys.len();
+        self.keys.retain(|(_, trusted)| trusted != key);
+        self.keys.len() != len
+    }
+
+    /// Vendor that owns `key`, `None` if the key is not trusted.
+    pub fn vendor(&self, key: &VerifyingKey) -> Option<&str> {
+        self.keys
+            .iter()
+            .find(|(_, trusted)| trusted == key)
+            .map(|(vendor, _)| vendor.as_str())
+    }
+
+    /// Checks signature embedded with [`sign_plugin`], returns vendor that signed the plugin.
+    pub fn verify(&self, bytecode: &[u8]) -> Result<&str, SignatureError> {
+        let (unsigned, signature) = split(bytecode)?;
+        let signature = PluginSignature::from_bytes(signature.ok_or(SignatureError::Unsigned)?)?;
+        self.verify_detached(unsigned, &signature)
+    }
+
+    /// Checks signature created with [`PluginSignature::sign`], returns vendor that signed the plugin.
+    pub fn verify_detached(
+        &self,
+        bytecode: &[u8],
+        signature: &PluginSignature,
+    ) -> Result<&str, SignatureError> {
+        let vendor = self
+            .vendor(&signature.key)
+            .ok_or(SignatureError::UntrustedKey(signature.key.to_bytes()))?;
+        signature.verify(bytecode)?;
+        Ok(vendor)
+    }
+}
+
+fn message(bytecode: &[u8]) -> Vec<u8> {
+    let mut message = CONTEXT.to_vec();
+    message.extend(Sha256::digest(bytecode));
+    message
+}
+
+/// Splits module into the signed part and the payload of the signature section.
+fn split(bytecode: &[u8]) -> Result<(&[u8], Option<&[u8]>), SignatureError> {
+    if bytecode.len() < WASM_HEADER_LEN || &bytecode[..4] != b"\0asm" {
+        return Err(SignatureError::Malformed);
+    }
+
+    let mut offset = WASM_HEADER_LEN;
+    while offset < bytecode.len() {
+        let start = offset;
+        let id = bytecode[offset];
+        offset += 1;
+
+        let size = read_leb128(bytecode, &mut offset)? as usize;
+        let end = offset
+            .checked_add(size)
+            .filter(|&end| end <= bytecode.len())
+            .ok_or(SignatureError::Malformed)?;
+
+        if id == 0 {
+            let name_len = read_leb128(bytecode, &mut offset)? as usize;
+            let name = bytecode
+                .get(offset..offset + name_len)
+                .filter(|_| offset + name_len <= end)
+                .ok_or(SignatureError::Malformed)?;
+
+            if name == SECTION_NAME.as_bytes() {
+                if end != bytecode.len() {
+                    return Err(SignatureError::Malformed);
+                }
+
+                return Ok((&bytecode[..start], Some(&bytecode[offset + name_len..end])));
+            }
+        }
+
+        offset = end;
+    }
+
+    Ok((bytecode, None))
+}
+
+fn read_leb128(bytes: &[u8], offset: &mut usize) -> Result<u32, SignatureError> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = *bytes.get(*offset).ok_or(SignatureError::Malformed)?;
+        *offset += 1;
+        value |= ((byte & 0x7f) as u32)
+            .checked_shl(shift)
+            .ok_or(SignatureError::Malformed)?;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(SignatureError::Malformed)
+}
+
+fn leb128(mut value: u32) -> Vec<u8> {
+    let mut bytes = vec![];
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return bytes;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
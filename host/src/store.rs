@@ -0,0 +1,260 @@
+use crate::{watchdog, ScotchHostError};
+use parking_lot::{Mutex, MutexGuard};
+#[cfg(feature = "async")]
+use std::cell::RefCell;
+use std::{
+    cell::Cell,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use wasmer::{AsStoreMut, Instance, RuntimeError, Store};
+use wasmer_middlewares::metering::{get_remaining_points, set_remaining_points, MeteringPoints};
+
+/// Global that metering middleware adds to instrumented modules.
+const REMAINING_POINTS: &str = "wasmer_metering_remaining_points";
+
+thread_local! {
+    /// Timeout set by `WasmPlugin::with_call_timeout` that overrides the plugin timeout.
+    static CALL_TIMEOUT: Cell<Option<Duration>> = const { Cell::new(None) };
+    /// Cancellation flag of the `GuestCall` that makes calls on this thread.
+    #[cfg(feature = "async")]
+    static CALL_CANCELLED: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+/// Limits applied to every guest call.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct CallLimits {
+    /// Fuel is refilled to this amount before every call.
+    pub(crate) fuel_per_call: Option<u64>,
+    /// Maximum amount of memory pages.
+    pub(crate) memory_limit: Option<u32>,
+}
+
+/// Store of a plugin. Every access to the store goes through the mutex,
+/// so calls from many threads are serialized.
+#[doc(hidden)]
+pub struct PluginStore {
+    store: Mutex<Store>,
+    limits: CallLimits,
+    timeout: Mutex<Option<Duration>>,
+    /// Module was compiled with interrupt checks, which timeouts require.
+    interruptible: bool,
+    /// Call that is running, shared with host functions.
+    running: Arc<RunningCall>,
+    /// Set by memories of the plugin when they fail to grow.
+    grow_denied: Arc<AtomicBool>,
+}
+
+impl PluginStore {
+    pub(crate) fn new(
+        store: Store,
+        limits: CallLimits,
+        timeout: Option<Duration>,
+        interruptible: bool,
+        running: Arc<RunningCall>,
+        grow_denied: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            store: Mutex::new(store),
+            limits,
+            timeout: Mutex::new(timeout),
+            interruptible,
+            running,
+            grow_denied,
+        }
+    }
+
+    #[inline]
+    pub fn lock(&self) -> MutexGuard<'_, Store> {
+        self.store.lock()
+    }
+
+    #[inline]
+    pub(crate) fn set_timeout(&self, timeout: Option<Duration>) {
+        *self.timeout.lock() = timeout;
+    }
+
+    /// Overrides the timeout of calls made by the current thread inside of `f`.
+    pub(crate) fn with_call_timeout<R>(timeout: Duration, f: impl FnOnce() -> R) -> R {
+        let _previous = CallTimeoutGuard(CALL_TIMEOUT.with(|t| t.replace(Some(timeout))));
+        f()
+    }
+
+    /// Calls made by the current thread inside of `f` are cancelled once `cancelled` is set.
+    #[cfg(feature = "async")]
+    pub(crate) fn with_cancellation<R>(cancelled: Arc<AtomicBool>, f: impl FnOnce() -> R) -> R {
+        let _previous = CancellationGuard(CALL_CANCELLED.with(|c| c.replace(Some(cancelled))));
+        f()
+    }
+
+    /// Runs a guest call on the current thread with the store locked for the whole duration of it.
+    /// If the call has a timeout, the watchdog interrupts it once the timeout elapses.
+    pub fn call<R>(
+        &self,
+        instance: &Instance,
+        call: impl FnOnce(&mut CallGuard) -> Result<R, RuntimeError>,
+    ) -> Result<R, RuntimeError> {
+        let timeout = CALL_TIMEOUT.with(Cell::get).or(*self.timeout.lock());
+        if timeout.is_some() && !self.interruptible {
+            return Err(ScotchHostError::InterruptMissing.into());
+        }
+
+        let mut store = self.store.lock();
+        if let Some(fuel) = self.limits.fuel_per_call {
+            refuel(&mut *store, instance, fuel)?;
+        }
+        self.grow_denied.store(false, Ordering::Release);
+
+        let token = Arc::new(CallToken::new());
+        if let Some(error) = token.interruption() {
+            return Err(error.into());
+        }
+
+        let mut guard = CallGuard { store };
+        let alarm = timeout.map(|timeout| watchdog::arm(timeout, token.clone()));
+
+        *self.running.0.lock() = Some(token.clone());
+        let out = call(&mut guard);
+        *self.running.0.lock() = None;
+
+        drop(alarm);
+        let CallGuard { mut store } = guard;
+
+        out.map_err(|e| {
+            if let Some(error) = token.interruption() {
+                error.into()
+            } else if matches!(
+                remaining_fuel(&mut *store, instance),
+                Some(MeteringPoints::Exhausted)
+            ) {
+                ScotchHostError::FuelExhausted.into()
+            } else if self.grow_denied.load(Ordering::Acquire) {
+                ScotchHostError::MemoryLimitExceeded.into()
+            } else {
+                e
+            }
+        })
+    }
+}
+
+/// Fuel left in the instance, `None` if the module was not instrumented with metering,
+/// e.g. it was compiled without fuel or by a shared engine.
+pub(crate) fn remaining_fuel(
+    store: &mut impl AsStoreMut,
+    instance: &Instance,
+) -> Option<MeteringPoints> {
+    instance.exports.get_global(REMAINING_POINTS).ok()?;
+    Some(get_remaining_points(store, instance))
+}
+
+/// Sets fuel left in the instance, fails if the module was not instrumented with metering.
+pub(crate) fn refuel(
+    store: &mut impl AsStoreMut,
+    instance: &Instance,
+    fuel: u64,
+) -> Result<(), ScotchHostError> {
+    instance
+        .exports
+        .get_global(REMAINING_POINTS)
+        .map_err(ScotchHostError::MeteringMissing)?;
+    set_remaining_points(store, instance, fuel);
+    Ok(())
+}
+
+/// Restores the timeout of the thread set by `with_call_timeout`, also if the call panics.
+struct CallTimeoutGuard(Option<Duration>);
+
+impl Drop for CallTimeoutGuard {
+    fn drop(&mut self) {
+        CALL_TIMEOUT.with(|t| t.set(self.0));
+    }
+}
+
+/// Restores the cancellation flag of the thread set by `with_cancellation`, also if the call panics.
+#[cfg(feature = "async")]
+struct CancellationGuard(Option<Arc<AtomicBool>>);
+
+#[cfg(feature = "async")]
+impl Drop for CancellationGuard {
+    fn drop(&mut self) {
+        CALL_CANCELLED.with(|c| *c.borrow_mut() = self.0.take());
+    }
+}
+
+/// Interruption state of a single guest call.
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct CallToken {
+    timed_out: AtomicBool,
+    /// Shared by all calls of one `GuestCall`.
+    #[cfg(feature = "async")]
+    cancelled: Option<Arc<AtomicBool>>,
+}
+
+impl CallToken {
+    fn new() -> Self {
+        Self {
+            timed_out: AtomicBool::new(false),
+            #[cfg(feature = "async")]
+            cancelled: CALL_CANCELLED.with(|c| c.borrow().clone()),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn time_out(&self) {
+        self.timed_out.store(true, Ordering::Release);
+    }
+
+    /// Error the call fails with if it timed out or was cancelled.
+    fn interruption(&self) -> Option<ScotchHostError> {
+        if self.timed_out.load(Ordering::Acquire) {
+            return Some(ScotchHostError::Timeout);
+        }
+
+        #[cfg(feature = "async")]
+        if let Some(cancelled) = &self.cancelled {
+            if cancelled.load(Ordering::Acquire) {
+                return Some(ScotchHostError::Cancelled);
+            }
+        }
+
+        None
+    }
+}
+
+/// Token of the call that is running, if any.
+#[doc(hidden)]
+#[derive(Debug, Default)]
+pub struct RunningCall(Mutex<Option<Arc<CallToken>>>);
+
+impl RunningCall {
+    /// Called by host functions, fails if the call they belong to was interrupted.
+    #[inline]
+    pub fn check(&self) -> Result<(), ScotchHostError> {
+        match self
+            .0
+            .lock()
+            .as_ref()
+            .and_then(|token| token.interruption())
+        {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Locked store of a plugin during a guest call.
+#[doc(hidden)]
+pub struct CallGuard<'a> {
+    store: MutexGuard<'a, Store>,
+}
+
+impl CallGuard<'_> {
+    #[inline]
+    pub fn store(&mut self) -> &mut Store {
+        &mut self.store
+    }
+}
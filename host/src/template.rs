@@ -0,0 +1,121 @@
+use crate::{plugin::BuilderTemplate, PluginPool, ScotchHostError, WasmPlugin, WasmPluginBuilder};
+use std::any::Any;
+use wasmer::{Engine, Module};
+
+#[cfg(feature = "signing")]
+use crate::TrustStore;
+
+type Configure<S> = Box<dyn Fn(WasmPluginBuilder<S>, S) -> WasmPluginBuilder<S> + Send + Sync>;
+
+/// Compiled plugin that cheaply creates new instances, each with its own state.
+/// Instances share the compiled code and the engine.
+/// ```ignore
+/// let template = PluginTemplate::new(PLUGIN_BYTES, |builder, state| {
+///     builder
+///         .with_state(state)
+///         .with_imports(make_imports![print])
+///         .with_exports(make_exports![add_up_list])
+/// })?;
+///
+/// let plugins = tenants
+///     .iter()
+///     .map(|tenant| template.instantiate(TenantState::new(tenant)))
+///     .collect::<Result<Vec<_>, _>>()?;
+/// ```
+pub struct PluginTemplate<S: Any + Send + Sized + 'static> {
+    template: BuilderTemplate,
+    configure: Configure<S>,
+}
+
+impl<S: Any + Send + Sized + 'static> PluginTemplate<S> {
+    /// Compiles `bytecode` with the default settings. `configure` has to pass the state
+    /// to `with_state` and set imports and exports, it is called for every instance.
+    #[cfg(feature = "compiler")]
+    #[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "compiler")))]
+    pub fn new(
+        bytecode: &[u8],
+        configure: impl Fn(WasmPluginBuilder<S>, S) -> WasmPluginBuilder<S> + Send + Sync + 'static,
+    ) -> Result<Self, ScotchHostError> {
+        Ok(Self::from_builder(
+            WasmPluginBuilder::new().from_binary(bytecode)?,
+            configure,
+        ))
+    }
+
+    /// Same as [`Self::new`] but `bytecode` has to be signed by a key from `trust`,
+    /// otherwise it is rejected with [`ScotchHostError::SignatureRejected`] before compilation.
+    #[cfg(feature = "signing")]
+    #[cfg_attr(feature = "unstable-doc-cfg", doc(cfg(feature = "signing")))]
+    pub fn new_signed(
+        bytecode: &[u8],
+        trust: &TrustStore,
+        configure: impl Fn(WasmPluginBuilder<S>, S) -> WasmPluginBuilder<S> + Send + Sync + 'static,
+    ) -> Result<Self, ScotchHostError> {
+        trust.verify(bytecode)?;
+        Self::new(bytecode, configure)
+    }
+
+    /// Creates template from a builder with compiled plugin, every instance gets its
+    /// compiler, fuel, memory, timeout and WASI settings. State, imports and exports of `builder`
+    /// are not used, `configure` sets them for every instance.
+    /// ```ignore
+    /// let builder = WasmPlugin::builder()
+    ///     .with_fuel_per_call(1_000_000)
+    ///     .with_memory_limit(16)
+    ///     .from_binary(PLUGIN_BYTES)?;
+    /// let template = PluginTemplate::from_builder(builder, |builder, state| {
+    ///     builder.with_state(state).with_exports(make_exports![add_up_list])
+    /// });
+    /// ```
+    pub fn from_builder(
+        builder: WasmPluginBuilder<S>,
+        configure: impl Fn(WasmPluginBuilder<S>, S) -> WasmPluginBuilder<S> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            template: builder.template(),
+            configure: Box::new(configure),
+        }
+    }
+
+    /// Creates template from a module compiled by `engine`.
+    pub fn from_module(
+        engine: impl Into<Engine>,
+        module: Module,
+        configure: impl Fn(WasmPluginBuilder<S>, S) -> WasmPluginBuilder<S> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            template: BuilderTemplate::new(engine.into(), module),
+            configure: Box::new(configure),
+        }
+    }
+
+    /// Creates a new instance of the plugin with its own store and state.
+    pub fn instantiate(&self, state: S) -> Result<WasmPlugin, ScotchHostError> {
+        let builder = WasmPluginBuilder::from_template(&self.template);
+        (self.configure)(builder, state).finish()
+    }
+
+    /// Creates a pool of `size` instances, `state` creates the state of every instance.
+    pub fn pool(
+        &self,
+        size: usize,
+        mut state: impl FnMut() -> S,
+    ) -> Result<PluginPool, ScotchHostError> {
+        let plugins = (0..size)
+            .map(|_| self.instantiate(state()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(PluginPool::from_plugins(plugins))
+    }
+
+    /// Engine that compiled the module.
+    #[inline]
+    pub fn engine(&self) -> &Engine {
+        self.template.engine()
+    }
+
+    /// Compiled module shared by the instances.
+    #[inline]
+    pub fn module(&self) -> &Module {
+        self.template.module()
+    }
+}
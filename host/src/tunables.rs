@@ -0,0 +1,153 @@
+use std::{
+    ptr::NonNull,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use wasmer::{
+    vm::{
+        MemoryError, MemoryStyle, TableStyle, VMMemory, VMMemoryDefinition, VMTable,
+        VMTableDefinition,
+    },
+    MemoryType, Pages, TableType, Tunables,
+};
+use wasmer_vm::{LinearMemory, Trap};
+
+/// Tunables that cap the maximum size of linear memories.
+pub(crate) struct LimitingTunables<T: Tunables> {
+    limit: Pages,
+    base: T,
+    /// Set when a memory fails to grow, shared with the plugin store.
+    grow_denied: Arc<AtomicBool>,
+}
+
+impl<T: Tunables> LimitingTunables<T> {
+    pub(crate) fn new(base: T, limit: Pages, grow_denied: Arc<AtomicBool>) -> Self {
+        Self {
+            limit,
+            base,
+            grow_denied,
+        }
+    }
+
+    /// Caps the maximum of memories that do not declare one or declare a bigger one.
+    fn adjust_memory(&self, requested: &MemoryType) -> MemoryType {
+        let mut adjusted = *requested;
+        match adjusted.maximum {
+            Some(max) if max <= self.limit => (),
+            _ => adjusted.maximum = Some(self.limit),
+        }
+        adjusted
+    }
+
+    fn validate_memory(&self, ty: &MemoryType) -> Result<(), MemoryError> {
+        if ty.minimum > self.limit {
+            return Err(MemoryError::Generic(format!(
+                "Plugin requires {} pages of memory but the limit is {} pages",
+                ty.minimum.0, self.limit.0
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn track(&self, memory: VMMemory) -> VMMemory {
+        VMMemory(Box::new(TrackedMemory {
+            inner: memory.0,
+            grow_denied: self.grow_denied.clone(),
+        }))
+    }
+}
+
+impl<T: Tunables> Tunables for LimitingTunables<T> {
+    fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        self.base.memory_style(&self.adjust_memory(memory))
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.table_style(table)
+    }
+
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<VMMemory, MemoryError> {
+        let adjusted = self.adjust_memory(ty);
+        self.validate_memory(&adjusted)?;
+        self.base
+            .create_host_memory(&adjusted, style)
+            .map(|memory| self.track(memory))
+    }
+
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<VMMemory, MemoryError> {
+        let adjusted = self.adjust_memory(ty);
+        self.validate_memory(&adjusted)?;
+        self.base
+            .create_vm_memory(&adjusted, style, vm_definition_location)
+            .map(|memory| self.track(memory))
+    }
+
+    fn create_host_table(&self, ty: &TableType, style: &TableStyle) -> Result<VMTable, String> {
+        self.base.create_host_table(ty, style)
+    }
+
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<VMTable, String> {
+        self.base.create_vm_table(ty, style, vm_definition_location)
+    }
+}
+
+/// Memory that reports failed attempts to grow it, guest allocators trap right after them.
+#[derive(Debug)]
+struct TrackedMemory {
+    inner: Box<dyn LinearMemory + 'static>,
+    grow_denied: Arc<AtomicBool>,
+}
+
+impl LinearMemory for TrackedMemory {
+    fn ty(&self) -> MemoryType {
+        self.inner.ty()
+    }
+
+    fn size(&self) -> Pages {
+        self.inner.size()
+    }
+
+    fn style(&self) -> MemoryStyle {
+        self.inner.style()
+    }
+
+    fn grow(&mut self, delta: Pages) -> Result<Pages, MemoryError> {
+        let grown = self.inner.grow(delta);
+        if grown.is_err() {
+            self.grow_denied.store(true, Ordering::Release);
+        }
+        grown
+    }
+
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        self.inner.vmmemory()
+    }
+
+    fn try_clone(&self) -> Option<Box<dyn LinearMemory + 'static>> {
+        Some(Box::new(Self {
+            inner: self.inner.try_clone()?,
+            grow_denied: self.grow_denied.clone(),
+        }))
+    }
+
+    unsafe fn initialize_with_data(&self, start: usize, data: &[u8]) -> Result<(), Trap> {
+        self.inner.initialize_with_data(start, data)
+    }
+}
@@ -0,0 +1,230 @@
+use crate::{ScotchHostError, WasmPlugin, WasmPluginBuilder};
+use ed25519_dalek::{Signature, Signer as _, SigningKey, VerifyingKey};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::{
+    any::Any,
+    error::Error,
+    fmt::{self, Display},
+};
+use wasmer::{SerializeError, Target};
+
+const MAGIC: &[u8; 8] = b"SCOTCHWM";
+const FORMAT_VERSION: u8 = 1;
+
+const AUTH_NONE: u8 = 0;
+const AUTH_HMAC: u8 = 1;
+const AUTH_ED25519: u8 = 2;
+
+/// Key that authenticates plugins serialized with [`WasmPlugin::serialize_verified`].
+pub enum ArtifactSigner {
+    /// HMAC-SHA256 with a secret key shared by the producer and the host.
+    Hmac(Vec<u8>),
+    /// Ed25519 signature, the host only needs the public key.
+    Ed25519(SigningKey),
+}
+
+/// Key that checks plugins loaded with [`WasmPluginBuilder::from_verified_serialized`].
+pub enum ArtifactVerifier {
+    /// HMAC-SHA256 with the key used by [`ArtifactSigner::Hmac`].
+    Hmac(Vec<u8>),
+    /// Public key of [`ArtifactSigner::Ed25519`].
+    Ed25519(VerifyingKey),
+}
+
+/// Reason a serialized plugin was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationError {
+    /// Data does not start with a header of [`WasmPlugin::serialize_verified`] or is truncated.
+    InvalidHeader,
+    /// Header was written by an incompatible version of scotch.
+    UnsupportedFormat(u8),
+    /// Plugin was serialized by another version of scotch.
+    ScotchVersionMismatch { expected: String, found: String },
+    /// Plugin was serialized by another version of wasmer.
+    WasmerVersionMismatch { expected: String, found: String },
+    /// Plugin was compiled for another target or CPU features.
+    TargetMismatch { expected: String, found: String },
+    /// Compiled code does not match the hash in the header.
+    HashMismatch,
+    /// Plugin is not authenticated or is authenticated with another kind of key.
+    Unsigned,
+    /// HMAC or signature does not match the key.
+    SignatureMismatch,
+}
+
+impl Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl Error for VerificationError {}
+
+impl WasmPlugin {
+    /// Same as [`WasmPlugin::serialize`] but prepends a header with versions of scotch and wasmer,
+    /// the target, a hash of the compiled code and, if `signer` is set, an HMAC or a signature.
+    /// Load it with [`WasmPluginBuilder::from_verified_serialized`].
+    pub fn serialize_verified(
+        &self,
+        signer: Option<&ArtifactSigner>,
+    ) -> Result<Vec<u8>, SerializeError> {
+        Ok(seal(&self.serialize()?, signer))
+    }
+}
+
+impl<S: Any + Send + Sized + 'static> WasmPluginBuilder<S> {
+    /// Creates plugin from bytes created by [`WasmPlugin::serialize_verified`].
+    /// Unlike [`Self::from_serialized`] this is safe, the compiled code is only deserialized
+    /// if it was authenticated with `verifier` and built by the same versions for the same target.
+    pub fn from_verified_serialized(
+        self,
+        data: &[u8],
+        verifier: &ArtifactVerifier,
+    ) -> Result<Self, ScotchHostError> {
+        let code = open(data, Some(verifier))?;
+        // SAFETY: Code was serialized by the owner of the key with the same wasmer for the same target.
+        Ok(unsafe { self.from_serialized(code)? })
+    }
+
+    /// Same as [`Self::from_verified_serialized`] but does not check the HMAC or signature,
+    /// so only corrupted data and mismatching versions or targets are detected.
+    /// # Safety
+    /// See [`Module::deserialize`], `data` must come from a trusted source.
+    pub unsafe fn from_checked_serialized(self, data: &[u8]) -> Result<Self, ScotchHostError> {
+        let code = open(data, None)?;
+        Ok(self.from_serialized(code)?)
+    }
+}
+
+/// Target the host compiles for, including CPU features.
+fn target() -> String {
+    let target = Target::default();
+    format!("{} {:?}", target.triple(), target.cpu_features())
+}
+
+/// Prepends header to the compiled code.
+fn seal(code: &[u8], signer: Option<&ArtifactSigner>) -> Vec<u8> {
+    let mut data = MAGIC.to_vec();
+    data.push(FORMAT_VERSION);
+    for field in [env!("CARGO_PKG_VERSION"), wasmer::VERSION, &target()] {
+        data.extend((field.len() as u16).to_le_bytes());
+        data.extend(field.as_bytes());
+    }
+    data.extend(Sha256::digest(code));
+
+    // Authenticates everything above, the hash covers the code.
+    match signer {
+        None => data.push(AUTH_NONE),
+        Some(ArtifactSigner::Hmac(key)) => {
+            let tag = hmac(key, &data).finalize().into_bytes();
+            data.push(AUTH_HMAC);
+            data.extend(tag);
+        }
+        Some(ArtifactSigner::Ed25519(key)) => {
+            let signature = key.sign(&data);
+            data.push(AUTH_ED25519);
+            data.extend(signature.to_bytes());
+        }
+    }
+
+    data.extend(code);
+    data
+}
+
+/// Checks header and returns the compiled code.
+/// HMAC or signature is only checked if `verifier` is set.
+fn open<'a>(
+    data: &'a [u8],
+    verifier: Option<&ArtifactVerifier>,
+) -> Result<&'a [u8], VerificationError> {
+    let mut reader = Reader(data);
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(VerificationError::InvalidHeader);
+    }
+
+    let format = reader.take(1)?[0];
+    if format != FORMAT_VERSION {
+        return Err(VerificationError::UnsupportedFormat(format));
+    }
+
+    reader.expect_field(env!("CARGO_PKG_VERSION"), |expected, found| {
+        VerificationError::ScotchVersionMismatch { expected, found }
+    })?;
+    reader.expect_field(wasmer::VERSION, |expected, found| {
+        VerificationError::WasmerVersionMismatch { expected, found }
+    })?;
+    reader.expect_field(&target(), |expected, found| {
+        VerificationError::TargetMismatch { expected, found }
+    })?;
+
+    let hash = reader.take(32)?;
+    let message = &data[..data.len() - reader.0.len()];
+
+    let auth = reader.take(1)?[0];
+    let valid = match (auth, verifier) {
+        (AUTH_NONE, None) => true,
+        (AUTH_HMAC, None) => reader.take(32).map(|_| true)?,
+        (AUTH_ED25519, None) => reader.take(Signature::BYTE_SIZE).map(|_| true)?,
+        (AUTH_HMAC, Some(ArtifactVerifier::Hmac(key))) => {
+            let tag = reader.take(32)?;
+            hmac(key, message).verify_slice(tag).is_ok()
+        }
+        (AUTH_ED25519, Some(ArtifactVerifier::Ed25519(key))) => {
+            let signature = reader.take(Signature::BYTE_SIZE)?;
+            let signature = Signature::from_slice(signature)
+                .map_err(|_| VerificationError::SignatureMismatch)?;
+            key.verify_strict(message, &signature).is_ok()
+        }
+        (AUTH_NONE | AUTH_HMAC | AUTH_ED25519, Some(_)) => return Err(VerificationError::Unsigned),
+        _ => return Err(VerificationError::InvalidHeader),
+    };
+    if !valid {
+        return Err(VerificationError::SignatureMismatch);
+    }
+
+    let code = reader.0;
+    if Sha256::digest(code).as_slice() != hash {
+        return Err(VerificationError::HashMismatch);
+    }
+
+    Ok(code)
+}
+
+fn hmac(key: &[u8], message: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], VerificationError> {
+        if self.0.len() < len {
+            return Err(VerificationError::InvalidHeader);
+        }
+
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    /// Reads length prefixed string and compares it with `expected`.
+    fn expect_field(
+        &mut self,
+        expected: &str,
+        mismatch: impl FnOnce(String, String) -> VerificationError,
+    ) -> Result<(), VerificationError> {
+        let len = u16::from_le_bytes(self.take(2)?.try_into().unwrap());
+        let found = self.take(len as usize)?;
+        if found != expected.as_bytes() {
+            return Err(mismatch(
+                expected.to_owned(),
+                String::from_utf8_lossy(found).into_owned(),
+            ));
+        }
+
+        Ok(())
+    }
+}
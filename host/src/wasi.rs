@@ -0,0 +1,150 @@
+use crate::ScotchHostError;
+use std::{io::Read, path::PathBuf};
+use wasmer::{Imports, Instance, InstantiationError, Module, Store};
+use wasmer_wasi::{Pipe, WasiFunctionEnv, WasiState};
+
+/// Configuration of WASI for plugins compiled for `wasm32-wasi`.
+/// ```ignore
+/// let plugin = WasmPlugin::builder()
+///     .with_state(())
+///     .with_wasi(
+///         WasiConfig::new()
+///             .arg("--verbose")
+///             .env("LOG", "debug")
+///             .map_dir("data", "./plugin-data")
+///             .capture_stdout(),
+///     )
+///     .from_binary(PLUGIN_BYTES)?
+///     .finish()?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct WasiConfig {
+    args: Vec<String>,
+    envs: Vec<(String, String)>,
+    dirs: Vec<(String, PathBuf)>,
+    capture_stdout: bool,
+    capture_stderr: bool,
+}
+
+impl WasiConfig {
+    /// Creates configuration without arguments, variables and directories.
+    /// Output of the plugin goes to stdout and stderr of the host.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds command line argument.
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Adds command line arguments.
+    pub fn args<I: Into<String>>(mut self, args: impl IntoIterator<Item = I>) -> Self {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Adds environment variable.
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.envs.push((key.into(), value.into()));
+        self
+    }
+
+    /// Allows plugin to access directory of the host under the same path.
+    pub fn preopen_dir(self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let alias = path.to_string_lossy().into_owned();
+        self.map_dir(alias, path)
+    }
+
+    /// Allows plugin to access directory of the host as `alias`.
+    pub fn map_dir(mut self, alias: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        self.dirs.push((alias.into(), path.into()));
+        self
+    }
+
+    /// Captures stdout of the plugin, read it with [`WasmPlugin::take_stdout`](crate::WasmPlugin::take_stdout).
+    pub fn capture_stdout(mut self) -> Self {
+        self.capture_stdout = true;
+        self
+    }
+
+    /// Captures stderr of the plugin, read it with [`WasmPlugin::take_stderr`](crate::WasmPlugin::take_stderr).
+    pub fn capture_stderr(mut self) -> Self {
+        self.capture_stderr = true;
+        self
+    }
+
+    /// Creates WASI environment and adds its functions to `imports`.
+    pub(crate) fn build(
+        self,
+        store: &mut Store,
+        module: &Module,
+        imports: &mut Imports,
+    ) -> Result<(WasiFunctionEnv, WasiOutput), ScotchHostError> {
+        let mut output = WasiOutput::default();
+        let mut state = WasiState::new(module.name().unwrap_or("plugin"));
+        state.args(&self.args).envs(self.envs);
+        for (alias, path) in self.dirs {
+            state.map_dir(&alias, path)?;
+        }
+
+        if self.capture_stdout {
+            let pipe = Pipe::new();
+            state.stdout(Box::new(pipe.clone()));
+            output.stdout = Some(pipe);
+        }
+
+        if self.capture_stderr {
+            let pipe = Pipe::new();
+            state.stderr(Box::new(pipe.clone()));
+            output.stderr = Some(pipe);
+        }
+
+        let env = state.finalize(store)?;
+        imports.extend(&env.import_object(store, module)?);
+
+        Ok((env, output))
+    }
+}
+
+/// Gives WASI access to the memory of the instance and runs the initializer of reactor modules.
+pub(crate) fn initialize(
+    env: &mut WasiFunctionEnv,
+    store: &mut Store,
+    instance: &Instance,
+) -> Result<(), ScotchHostError> {
+    env.initialize(store, instance)
+        .map_err(ScotchHostError::MemoryMissing)?;
+
+    if let Ok(init) = instance.exports.get_function("_initialize") {
+        init.call(store, &[]).map_err(InstantiationError::Start)?;
+    }
+
+    Ok(())
+}
+
+/// Captured output of a plugin.
+#[derive(Debug, Default)]
+pub(crate) struct WasiOutput {
+    stdout: Option<Pipe>,
+    stderr: Option<Pipe>,
+}
+
+impl WasiOutput {
+    pub(crate) fn take_stdout(&self) -> Option<Vec<u8>> {
+        Self::take(self.stdout.as_ref()?)
+    }
+
+    pub(crate) fn take_stderr(&self) -> Option<Vec<u8>> {
+        Self::take(self.stderr.as_ref()?)
+    }
+
+    fn take(pipe: &Pipe) -> Option<Vec<u8>> {
+        let mut buf = vec![];
+        pipe.clone().read_to_end(&mut buf).ok()?;
+        Some(buf)
+    }
+}
@@ -0,0 +1,77 @@
+use crate::store::CallToken;
+use parking_lot::{Condvar, Mutex};
+use std::{
+    sync::{Arc, Once},
+    thread,
+    time::{Duration, Instant},
+};
+
+static WATCHDOG: Watchdog = Watchdog {
+    deadlines: Mutex::new(Vec::new()),
+    changed: Condvar::new(),
+};
+static STARTED: Once = Once::new();
+
+struct Deadline {
+    at: Instant,
+    token: Arc<CallToken>,
+}
+
+/// Thread that marks guest calls which took longer than their timeout as timed out.
+/// Calls trap at the next interrupt check compiled into the guest or at the next host function.
+struct Watchdog {
+    deadlines: Mutex<Vec<Deadline>>,
+    changed: Condvar,
+}
+
+impl Watchdog {
+    fn run(&self) {
+        let mut deadlines = self.deadlines.lock();
+        loop {
+            let now = Instant::now();
+            deadlines.retain(|deadline| {
+                if deadline.at <= now {
+                    deadline.token.time_out();
+                }
+                deadline.at > now
+            });
+
+            match deadlines.iter().map(|deadline| deadline.at).min() {
+                Some(at) => _ = self.changed.wait_until(&mut deadlines, at),
+                None => self.changed.wait(&mut deadlines),
+            }
+        }
+    }
+}
+
+/// Marks the call of `token` as timed out once `timeout` elapses.
+pub(crate) fn arm(timeout: Duration, token: Arc<CallToken>) -> Alarm {
+    STARTED.call_once(|| {
+        thread::Builder::new()
+            .name("scotch-watchdog".into())
+            .spawn(|| WATCHDOG.run())
+            .expect("Failed to spawn watchdog thread");
+    });
+
+    WATCHDOG.deadlines.lock().push(Deadline {
+        at: Instant::now() + timeout,
+        token: token.clone(),
+    });
+    WATCHDOG.changed.notify_one();
+
+    Alarm { token }
+}
+
+/// Alarm of a running call, disarmed when dropped.
+pub(crate) struct Alarm {
+    token: Arc<CallToken>,
+}
+
+impl Drop for Alarm {
+    fn drop(&mut self) {
+        WATCHDOG
+            .deadlines
+            .lock()
+            .retain(|deadline| !Arc::ptr_eq(&deadline.token, &self.token));
+    }
+}
@@ -0,0 +1,73 @@
+#![cfg(feature = "cache")]
+
+mod common;
+
+use scotch_host::{guest_functions, make_exports, ModuleCache, WasmPlugin, WasmPluginBuilder};
+use std::{
+    fs::{self, File},
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+#[guest_functions]
+extern "C" {
+    pub fn echo_u32(value: u32) -> u32;
+}
+
+fn load(builder: WasmPluginBuilder<()>, cache: &ModuleCache) {
+    let plugin = common::load(builder.with_cache(cache.clone()))
+        .with_exports(make_exports![echo_u32])
+        .finish()
+        .unwrap();
+    assert_eq!(plugin.function_unwrap::<echo_u32>()(7).unwrap(), 7);
+}
+
+fn entries(cache: &ModuleCache) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(cache.dir()) else {
+        return Vec::new();
+    };
+    entries.map(|entry| entry.unwrap().path()).collect()
+}
+
+fn modified(path: &PathBuf) -> SystemTime {
+    fs::metadata(path).unwrap().modified().unwrap()
+}
+
+#[test]
+fn cache_hits_and_misses() {
+    let dir = std::env::temp_dir().join(format!("scotch-cache-{}", std::process::id()));
+    let cache = ModuleCache::new(&dir);
+    cache.clear().unwrap();
+
+    // Miss, compiled plugin is stored.
+    load(WasmPlugin::builder(), &cache);
+    let [entry] = &entries(&cache)[..] else {
+        panic!("Expected one cache entry");
+    };
+
+    // Hit, entry is not written again.
+    let past = SystemTime::now() - Duration::from_secs(3600);
+    File::options()
+        .write(true)
+        .open(entry)
+        .unwrap()
+        .set_modified(past)
+        .unwrap();
+    load(WasmPlugin::builder(), &cache);
+    assert_eq!(entries(&cache), std::slice::from_ref(entry));
+    assert_eq!(modified(entry), past);
+
+    // Different settings miss.
+    load(WasmPlugin::builder().with_fuel(1_000_000), &cache);
+    assert_eq!(entries(&cache).len(), 2);
+
+    // Corrupted entry is compiled and stored again.
+    fs::write(entry, b"corrupted").unwrap();
+    load(WasmPlugin::builder(), &cache);
+    assert!(fs::metadata(entry).unwrap().len() > 9);
+    assert!(modified(entry) > past);
+
+    cache.clear().unwrap();
+    assert!(entries(&cache).is_empty());
+    fs::remove_dir(dir).unwrap();
+}
@@ -0,0 +1,116 @@
+#![cfg(feature = "async")]
+
+mod common;
+
+use scotch_host::{guest_functions, make_exports, ScotchHostError, WasmPlugin};
+use std::{sync::mpsc, sync::Arc, time::Duration};
+
+#[guest_functions]
+extern "C" {
+    pub fn spin();
+    pub fn spin_host();
+    pub fn call_host(name: &String) -> String;
+}
+
+#[test]
+fn dropping_guest_call_cancels_only_that_call() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let plugin = Arc::new(
+        common::load(WasmPlugin::builder())
+            .with_exports(make_exports![spin, spin_host, call_host])
+            .finish()
+            .unwrap(),
+    );
+
+    runtime.block_on(async {
+        let (tx, rx) = mpsc::channel();
+        let spinning = plugin.call_async::<spin_host, _>(move |f| _ = tx.send(f()));
+        std::thread::sleep(Duration::from_millis(50));
+        // Waits for the spinning call, which holds the store.
+        let queued = plugin.call_async::<call_host, _>(|f| f(&"Jack".to_owned()));
+        std::thread::sleep(Duration::from_millis(50));
+        drop(spinning);
+
+        let error = rx
+            .recv_timeout(Duration::from_secs(10))
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast::<ScotchHostError>(),
+            Ok(ScotchHostError::Cancelled)
+        ));
+        assert!(queued.await.unwrap().unwrap().contains("Hello, Jack!"));
+
+        let text = plugin
+            .call_async::<call_host, _>(|f| f(&"Jill".to_owned()))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(text.contains("Hello, Jill!"));
+    });
+}
+
+#[test]
+fn dropping_guest_call_interrupts_pure_compute() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let plugin = Arc::new(
+        common::load(WasmPlugin::builder())
+            .with_exports(make_exports![spin, call_host])
+            .finish()
+            .unwrap(),
+    );
+
+    runtime.block_on(async {
+        let (tx, rx) = mpsc::channel();
+        let spinning = plugin.call_async::<spin, _>(move |f| _ = tx.send(f()));
+        std::thread::sleep(Duration::from_millis(50));
+        drop(spinning);
+
+        let error = rx
+            .recv_timeout(Duration::from_secs(10))
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast::<ScotchHostError>(),
+            Ok(ScotchHostError::Cancelled)
+        ));
+
+        let text = plugin
+            .call_async::<call_host, _>(|f| f(&"Jill".to_owned()))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(text.contains("Hello, Jill!"));
+    });
+}
+
+#[test]
+fn guest_call_fails_when_runtime_is_shut_down() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let handle = runtime.handle().clone();
+    runtime.shutdown_background();
+    let plugin = Arc::new(
+        common::load(WasmPlugin::builder())
+            .with_exports(make_exports![call_host])
+            .finish()
+            .unwrap(),
+    );
+
+    // Task is cancelled by tokio instead of running.
+    let call = {
+        let _runtime = handle.enter();
+        plugin.call_async::<call_host, _>(|f| f(&"Jack".to_owned()))
+    };
+    let error = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(call)
+        .unwrap_err();
+    assert!(matches!(error, ScotchHostError::Cancelled));
+}
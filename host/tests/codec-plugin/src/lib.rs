@@ -0,0 +1,25 @@
+//! Plugin used by the integration tests of `scotch-host` that encodes values with `postcard`.
+
+scotch_guest::export_alloc!(codec = scotch_guest::Postcard);
+
+#[scotch_guest::host_functions(codec = scotch_guest::Postcard)]
+extern "C" {
+    fn host_greet(name: &String) -> String;
+    fn host_push(list: &mut Vec<i32>, value: i32);
+}
+
+#[scotch_guest::guest_function(codec = scotch_guest::Postcard)]
+fn add_up_list(items: &Vec<i32>) -> i32 {
+    items.iter().sum::<i32>()
+}
+
+#[scotch_guest::guest_function(codec = scotch_guest::Postcard)]
+fn greet(name: &String) -> String {
+    host_greet(name)
+}
+
+#[scotch_guest::guest_function(codec = scotch_guest::Postcard)]
+fn push_twice(list: &mut Vec<i32>, value: i32) {
+    host_push(list, value);
+    host_push(list, value);
+}
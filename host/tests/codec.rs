@@ -0,0 +1,89 @@
+#![cfg(feature = "postcard")]
+
+mod common;
+
+use scotch_host::{
+    guest_functions, host_function, make_exports, make_imports, CodecId, Postcard, ScotchHostError,
+    WasmPlugin, WasmPluginBuilder,
+};
+
+#[guest_functions(codec = scotch_host::Postcard)]
+extern "C" {
+    pub fn add_up_list(items: &Vec<i32>) -> i32;
+    pub fn greet(name: &String) -> String;
+    pub fn push_twice(list: &mut Vec<i32>, value: i32);
+}
+
+#[guest_functions]
+extern "C" {
+    pub fn bincode_add_up_list(items: &Vec<i32>) -> i32;
+}
+
+#[host_function(codec = scotch_host::Postcard)]
+fn host_greet(name: &String) -> String {
+    format!("Hello, {name}")
+}
+
+#[host_function(codec = scotch_host::Postcard)]
+fn host_push(list: &mut Vec<i32>, value: i32) {
+    list.push(value);
+}
+
+fn builder() -> WasmPluginBuilder<()> {
+    WasmPlugin::builder()
+        .with_state(())
+        .with_imports(make_imports![host_greet, host_push])
+        .from_binary(common::codec_plugin_bytes())
+        .unwrap()
+}
+
+#[test]
+fn postcard_values_are_passed_both_ways() {
+    let plugin = builder()
+        .with_codec::<Postcard>()
+        .with_exports(make_exports![add_up_list, greet, push_twice])
+        .finish()
+        .unwrap();
+
+    assert_eq!(
+        plugin.function_unwrap::<add_up_list>()(&vec![1, 2, 3]).unwrap(),
+        6
+    );
+    assert_eq!(
+        plugin.function_unwrap::<greet>()(&"guest".to_owned()).unwrap(),
+        "Hello, guest"
+    );
+
+    let mut list = vec![0];
+    plugin.function_unwrap::<push_twice>()(&mut list, 4).unwrap();
+    assert_eq!(list, [0, 4, 4]);
+}
+
+#[test]
+fn plugin_with_other_codec_is_rejected() {
+    let error = builder().finish().err().unwrap();
+    assert!(matches!(
+        error,
+        ScotchHostError::CodecMismatch {
+            expected: CodecId::Bincode,
+            found: Some(CodecId::Postcard),
+        }
+    ));
+}
+
+#[test]
+fn exports_with_other_codec_are_rejected() {
+    let error = builder()
+        .with_codec::<Postcard>()
+        .with_exports(make_exports![add_up_list, bincode_add_up_list])
+        .finish()
+        .err()
+        .unwrap();
+    assert!(matches!(
+        error,
+        ScotchHostError::CodecMismatch {
+            expected: CodecId::Postcard,
+            found: Some(CodecId::Bincode),
+        }
+    ));
+}
@@ -0,0 +1,120 @@
+#![allow(dead_code)]
+
+use scotch_host::{host_function, make_imports, WasmPluginBuilder};
+use std::{fs, process::Command, sync::OnceLock};
+
+const TARGET_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../target/test-plugin");
+
+/// Bytecode of `tests/plugin`, built for `wasm32-unknown-unknown` on first use.
+pub fn plugin_bytes() -> &'static [u8] {
+    static BYTES: OnceLock<Vec<u8>> = OnceLock::new();
+    BYTES.get_or_init(|| build("test-plugin"))
+}
+
+/// Bytecode of `tests/wasi-plugin`, built for `wasm32-unknown-unknown` on first use.
+pub fn wasi_plugin_bytes() -> &'static [u8] {
+    static BYTES: OnceLock<Vec<u8>> = OnceLock::new();
+    BYTES.get_or_init(|| build("test-wasi-plugin"))
+}
+
+/// Bytecode of `tests/codec-plugin`, built for `wasm32-unknown-unknown` on first use.
+pub fn codec_plugin_bytes() -> &'static [u8] {
+    static BYTES: OnceLock<Vec<u8>> = OnceLock::new();
+    BYTES.get_or_init(|| build("test-codec-plugin"))
+}
+
+fn build(package: &str) -> Vec<u8> {
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "--release", "--package", package])
+        .args([
+            "--target",
+            "wasm32-unknown-unknown",
+            "--target-dir",
+            TARGET_DIR,
+        ])
+        .status()
+        .expect("Failed to run cargo");
+    assert!(status.success(), "Failed to build {package}");
+
+    fs::read(format!(
+        "{TARGET_DIR}/wasm32-unknown-unknown/release/{}.wasm",
+        package.replace('-', "_")
+    ))
+    .unwrap_or_else(|_| panic!("Failed to read {package}"))
+}
+
+/// Compiles the test plugin with `builder` and provides its imports.
+pub fn load(builder: WasmPluginBuilder<()>) -> WasmPluginBuilder<()> {
+    with_imports(
+        builder
+            .with_state(())
+            .from_binary(plugin_bytes())
+            .expect("Failed to compile the test plugin"),
+    )
+}
+
+/// Provides host functions imported by the test plugin.
+pub fn with_imports(builder: WasmPluginBuilder<()>) -> WasmPluginBuilder<()> {
+    builder.with_imports(make_imports![
+        host_not,
+        host_next_char,
+        host_scale,
+        host_offset,
+        host_wide,
+        host_greet,
+        host_pair,
+        host_push,
+        host_checked,
+        host_len
+    ])
+}
+
+#[host_function]
+fn host_not(value: bool) -> bool {
+    !value
+}
+
+#[host_function]
+fn host_next_char(value: char) -> char {
+    char::from_u32(value as u32 + 1).unwrap()
+}
+
+#[host_function]
+fn host_scale(value: f64, factor: f32) -> f64 {
+    value * factor as f64
+}
+
+#[host_function]
+fn host_offset(value: usize, offset: isize) -> usize {
+    value.wrapping_add_signed(offset)
+}
+
+#[host_function]
+fn host_wide(value: u128, delta: i128) -> i128 {
+    value as i128 + delta
+}
+
+#[host_function]
+fn host_greet(name: &String) -> String {
+    format!("Hello, {name}!")
+}
+
+#[host_function]
+fn host_pair(pair: &(u8, String)) -> (u8, String) {
+    (pair.0 * 2, pair.1.to_uppercase())
+}
+
+#[host_function]
+fn host_push(list: &mut Vec<i32>, value: i32) {
+    list.push(value);
+}
+
+#[host_function]
+fn host_checked(value: u32) -> Result<u32, String> {
+    value.checked_sub(1).ok_or_else(|| "Underflow".to_owned())
+}
+
+#[host_function]
+fn host_len(text: &str) -> usize {
+    text.len()
+}
@@ -0,0 +1,212 @@
+mod common;
+
+use scotch_host::{
+    guest_functions, make_exports, Compiler, OptLevel, RuntimeError, ScotchHostError, WasmPlugin,
+    WasmPluginBuilder,
+};
+use std::time::{Duration, Instant};
+use wasmer::Store;
+
+#[guest_functions]
+extern "C" {
+    pub fn burn(iterations: u64) -> u64;
+    pub fn allocate(pages: u32) -> u32;
+    pub fn spin();
+    pub fn spin_host();
+}
+
+fn host_error(error: RuntimeError) -> ScotchHostError {
+    error
+        .downcast::<ScotchHostError>()
+        .expect("Call failed with a trap instead of a host error")
+}
+
+#[test]
+fn fuel_is_consumed_and_exhausted() {
+    let plugin = common::load(WasmPlugin::builder().with_fuel(1_000_000))
+        .with_exports(make_exports![burn])
+        .finish()
+        .unwrap();
+
+    let start = plugin.fuel().unwrap();
+    plugin.function_unwrap::<burn>()(10).unwrap();
+    let left = plugin.fuel().unwrap();
+    assert!(left < start);
+
+    let error = plugin.function_unwrap::<burn>()(10_000_000).unwrap_err();
+    assert!(matches!(host_error(error), ScotchHostError::FuelExhausted));
+    assert_eq!(plugin.fuel(), Some(0));
+
+    plugin.set_fuel(1_000_000).unwrap();
+    assert_eq!(plugin.fuel(), Some(1_000_000));
+    plugin.function_unwrap::<burn>()(10).unwrap();
+}
+
+#[test]
+fn fuel_per_call_is_refilled() {
+    let plugin = common::load(WasmPlugin::builder().with_fuel_per_call(100_000))
+        .with_exports(make_exports![burn])
+        .finish()
+        .unwrap();
+
+    for _ in 0..100 {
+        plugin.function_unwrap::<burn>()(1_000).unwrap();
+    }
+
+    let error = plugin.function_unwrap::<burn>()(1_000_000).unwrap_err();
+    assert!(matches!(host_error(error), ScotchHostError::FuelExhausted));
+    plugin.function_unwrap::<burn>()(1_000).unwrap();
+}
+
+#[test]
+fn fuel_is_unavailable_without_metering() {
+    let plugin = common::load(WasmPlugin::builder())
+        .with_exports(make_exports![burn])
+        .finish()
+        .unwrap();
+    assert_eq!(plugin.fuel(), None);
+    assert!(matches!(
+        plugin.set_fuel(10),
+        Err(ScotchHostError::MeteringMissing(_))
+    ));
+
+    // Serialized modules are only metered if they were compiled with fuel.
+    let serialized = plugin.serialize().unwrap();
+    let builder = WasmPlugin::builder().with_fuel(10).with_state(());
+    let plugin = common::with_imports(unsafe { builder.from_serialized(&serialized).unwrap() })
+        .with_exports(make_exports![burn])
+        .finish()
+        .unwrap();
+    assert_eq!(plugin.fuel(), None);
+    plugin.function_unwrap::<burn>()(1_000).unwrap();
+}
+
+#[test]
+fn memory_limit_is_enforced() {
+    let plugin = common::load(WasmPlugin::builder().with_memory_limit(64))
+        .with_exports(make_exports![allocate])
+        .finish()
+        .unwrap();
+
+    assert_eq!(plugin.function_unwrap::<allocate>()(8).unwrap(), 8);
+    let error = plugin.function_unwrap::<allocate>()(128).unwrap_err();
+    assert!(matches!(
+        host_error(error),
+        ScotchHostError::MemoryLimitExceeded
+    ));
+    assert_eq!(plugin.function_unwrap::<allocate>()(8).unwrap(), 8);
+
+    let unlimited = common::load(WasmPlugin::builder())
+        .with_exports(make_exports![allocate])
+        .finish()
+        .unwrap();
+    assert_eq!(unlimited.function_unwrap::<allocate>()(128).unwrap(), 128);
+}
+
+#[test]
+fn memory_limit_is_checked_on_instantiation() {
+    let error = common::load(WasmPlugin::builder().with_memory_limit(1))
+        .finish()
+        .err()
+        .unwrap();
+    assert!(matches!(error, ScotchHostError::InstantiationFailed(_)));
+
+    let error = common::load(
+        WasmPlugin::builder()
+            .with_memory_limit(64)
+            .with_initial_memory(128),
+    )
+    .finish()
+    .err()
+    .unwrap();
+    assert!(matches!(error, ScotchHostError::MemoryLimitExceeded));
+
+    common::load(WasmPlugin::builder().with_initial_memory(64))
+        .finish()
+        .unwrap();
+}
+
+#[test]
+fn timeout_interrupts_guest() {
+    let plugin = common::load(WasmPlugin::builder().with_timeout(Duration::from_millis(50)))
+        .with_exports(make_exports![burn, spin])
+        .finish()
+        .unwrap();
+
+    let start = Instant::now();
+    let error = plugin.function_unwrap::<spin>()().unwrap_err();
+    assert!(matches!(host_error(error), ScotchHostError::Timeout));
+    assert!(start.elapsed() < Duration::from_secs(5));
+    plugin.function_unwrap::<burn>()(1_000).unwrap();
+
+    plugin.set_timeout(None);
+    let error = plugin
+        .with_call_timeout(Duration::from_millis(10), || {
+            plugin.function_unwrap::<spin>()()
+        })
+        .unwrap_err();
+    assert!(matches!(host_error(error), ScotchHostError::Timeout));
+    plugin.function_unwrap::<burn>()(1_000).unwrap();
+}
+
+#[test]
+fn timeout_keeps_fuel() {
+    let fuel = 1_000_000_000_000;
+    let plugin = common::load(
+        WasmPlugin::builder()
+            .with_fuel(fuel)
+            .with_timeout(Duration::from_millis(20)),
+    )
+    .with_exports(make_exports![burn, spin])
+    .finish()
+    .unwrap();
+
+    let error = plugin.function_unwrap::<spin>()().unwrap_err();
+    assert!(matches!(host_error(error), ScotchHostError::Timeout));
+    let left = plugin.fuel().unwrap();
+    assert!(0 < left && left < fuel);
+    plugin.function_unwrap::<burn>()(1_000).unwrap();
+}
+
+#[test]
+fn timeout_interrupts_guest_without_metering() {
+    let engine = Compiler::default().engine(OptLevel::default());
+    let plugin = common::load(
+        WasmPluginBuilder::new_with_engine(engine).with_timeout(Duration::from_millis(20)),
+    )
+    .with_exports(make_exports![burn, spin, spin_host])
+    .finish()
+    .unwrap();
+    assert_eq!(plugin.fuel(), None);
+
+    let error = plugin.function_unwrap::<spin>()().unwrap_err();
+    assert!(matches!(host_error(error), ScotchHostError::Timeout));
+    let error = plugin.function_unwrap::<spin_host>()().unwrap_err();
+    assert!(matches!(host_error(error), ScotchHostError::Timeout));
+    plugin.function_unwrap::<burn>()(1_000).unwrap();
+}
+
+#[test]
+fn timeout_requires_interrupt_checks() {
+    let builder = || WasmPluginBuilder::new_with_store(Store::default());
+    let error = common::load(builder().with_timeout(Duration::from_millis(20)))
+        .finish()
+        .err()
+        .unwrap();
+    assert!(matches!(error, ScotchHostError::InterruptMissing));
+
+    let plugin = common::load(builder())
+        .with_exports(make_exports![burn])
+        .finish()
+        .unwrap();
+    plugin.function_unwrap::<burn>()(1_000).unwrap();
+    let error = plugin
+        .with_call_timeout(Duration::from_millis(20), || {
+            plugin.function_unwrap::<burn>()(1_000)
+        })
+        .unwrap_err();
+    assert!(matches!(
+        host_error(error),
+        ScotchHostError::InterruptMissing
+    ));
+}
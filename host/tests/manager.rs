@@ -0,0 +1,86 @@
+mod common;
+
+use scotch_host::{guest_functions, make_exports, PluginManager, ScotchHostError};
+use std::{fs, path::PathBuf};
+
+#[guest_functions]
+extern "C" {
+    pub fn echo_u32(value: u32) -> u32;
+    pub fn not_exported(value: u32) -> u32;
+}
+
+fn plugin_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("scotch-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn load_dir_keeps_loading_after_bad_file() {
+    let dir = plugin_dir("manager");
+    fs::write(dir.join("a.wasm"), common::plugin_bytes()).unwrap();
+    fs::write(dir.join("b.wasm"), b"not a plugin").unwrap();
+    fs::write(dir.join("c.wasm"), common::plugin_bytes()).unwrap();
+    fs::write(dir.join("notes.txt"), b"skipped").unwrap();
+
+    let mut manager = PluginManager::new();
+    let loaded = manager
+        .load_dir(&dir, |_name, builder| {
+            common::with_imports(builder.with_state(()))
+        })
+        .unwrap();
+
+    let names = loaded
+        .iter()
+        .map(|(path, _)| path.file_name().unwrap().to_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["a.wasm", "b.wasm", "c.wasm"]);
+    assert!(matches!(
+        loaded[1].1,
+        Err(ScotchHostError::CompileFailed(_))
+    ));
+
+    let a = *loaded[0].1.as_ref().unwrap();
+    let c = *loaded[2].1.as_ref().unwrap();
+    assert_eq!(manager.len(), 2);
+    assert_eq!(manager.name(a), Some("a"));
+    assert_eq!(manager.find("c"), Some(c));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn broadcast_calls_every_plugin_that_exports_function() {
+    let mut manager = PluginManager::new();
+    let cached = manager
+        .load("cached", common::plugin_bytes(), |builder| {
+            common::with_imports(builder.with_state(())).with_exports(make_exports![echo_u32])
+        })
+        .unwrap();
+    let resolved = manager
+        .load("resolved", common::plugin_bytes(), |builder| {
+            common::with_imports(builder.with_state(()))
+        })
+        .unwrap();
+
+    let manager = &manager;
+    let out = manager.broadcast::<echo_u32, _>(|f| f(5).unwrap());
+    assert_eq!(out, [(cached, 5), (resolved, 5)]);
+    assert!(manager.broadcast::<not_exported, _>(|f| f(5)).is_empty());
+}
+
+#[test]
+fn removed_plugin_is_not_found() {
+    let mut manager = PluginManager::new();
+    let id = manager
+        .load("echo", common::plugin_bytes(), |builder| {
+            common::with_imports(builder.with_state(()))
+        })
+        .unwrap();
+
+    assert!(manager.remove(id).is_some());
+    assert!(manager.is_empty());
+    assert_eq!(manager.find("echo"), None);
+    assert!(manager.get(id).is_none());
+}
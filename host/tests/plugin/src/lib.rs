@@ -0,0 +1,190 @@
+//! Plugin used by the integration tests of `scotch-host`.
+
+scotch_guest::export_alloc!();
+
+#[scotch_guest::host_functions]
+extern "C" {
+    fn host_not(value: bool) -> bool;
+    fn host_next_char(value: char) -> char;
+    fn host_scale(value: f64, factor: f32) -> f64;
+    fn host_offset(value: usize, offset: isize) -> usize;
+    fn host_wide(value: u128, delta: i128) -> i128;
+    fn host_greet(name: &String) -> String;
+    fn host_pair(pair: &(u8, String)) -> (u8, String);
+    fn host_push(list: &mut Vec<i32>, value: i32);
+    fn host_checked(value: u32) -> Result<u32, String>;
+    fn host_len(text: &str) -> usize;
+}
+
+#[scotch_guest::guest_function]
+fn echo_bool(value: bool) -> bool {
+    value
+}
+
+#[scotch_guest::guest_function]
+fn echo_char(value: char) -> char {
+    value
+}
+
+#[scotch_guest::guest_function]
+fn echo_u8(value: u8) -> u8 {
+    value
+}
+
+#[scotch_guest::guest_function]
+fn echo_u16(value: u16) -> u16 {
+    value
+}
+
+#[scotch_guest::guest_function]
+fn echo_u32(value: u32) -> u32 {
+    value
+}
+
+#[scotch_guest::guest_function]
+fn echo_u64(value: u64) -> u64 {
+    value
+}
+
+#[scotch_guest::guest_function]
+fn echo_i8(value: i8) -> i8 {
+    value
+}
+
+#[scotch_guest::guest_function]
+fn echo_i16(value: i16) -> i16 {
+    value
+}
+
+#[scotch_guest::guest_function]
+fn echo_i32(value: i32) -> i32 {
+    value
+}
+
+#[scotch_guest::guest_function]
+fn echo_i64(value: i64) -> i64 {
+    value
+}
+
+#[scotch_guest::guest_function]
+fn echo_f32(value: f32) -> f32 {
+    value
+}
+
+#[scotch_guest::guest_function]
+fn echo_f64(value: f64) -> f64 {
+    value
+}
+
+#[scotch_guest::guest_function]
+fn echo_usize(value: usize) -> usize {
+    value
+}
+
+#[scotch_guest::guest_function]
+fn echo_isize(value: isize) -> isize {
+    value
+}
+
+#[scotch_guest::guest_function]
+fn echo_u128(value: u128) -> u128 {
+    value
+}
+
+#[scotch_guest::guest_function]
+fn echo_i128(value: i128) -> i128 {
+    value
+}
+
+#[scotch_guest::guest_function]
+fn echo_string(value: &String) -> String {
+    value.clone()
+}
+
+#[scotch_guest::guest_function]
+fn echo_vec(value: &Vec<i32>) -> Vec<i32> {
+    value.clone()
+}
+
+#[scotch_guest::guest_function]
+fn echo_tuple(value: &(u8, String)) -> (u8, String) {
+    value.clone()
+}
+
+#[scotch_guest::guest_function]
+fn echo_array(value: &[u32; 3]) -> [u32; 3] {
+    *value
+}
+
+#[scotch_guest::guest_function]
+fn echo_option(value: &Option<i64>) -> Option<i64> {
+    *value
+}
+
+#[scotch_guest::guest_function]
+fn echo_bytes(value: &[u8]) -> Vec<u8> {
+    value.to_vec()
+}
+
+#[scotch_guest::guest_function]
+fn echo_str(value: &str) -> String {
+    value.to_owned()
+}
+
+#[scotch_guest::guest_function]
+fn checked_div(a: i32, b: i32) -> Result<i32, String> {
+    a.checked_div(b)
+        .ok_or_else(|| "Division by zero".to_owned())
+}
+
+#[scotch_guest::guest_function]
+fn push_range(list: &mut Vec<i32>, count: u32) {
+    list.extend(0..count as i32);
+}
+
+#[scotch_guest::guest_function]
+fn call_host(name: &String) -> String {
+    let mut list = vec![1];
+    host_push(&mut list, 2);
+    let (byte, text) = host_pair(&(7, name.clone()));
+
+    format!(
+        "{} {} {} {} {} {} {list:?} {byte} {text} {:?} {:?} {}",
+        host_not(false),
+        host_next_char('a'),
+        host_scale(1.5, 2.),
+        host_offset(10, -3),
+        host_wide(u64::MAX as u128, 1),
+        host_greet(name),
+        host_checked(1),
+        host_checked(0),
+        host_len(name),
+    )
+}
+
+#[scotch_guest::guest_function]
+fn burn(iterations: u64) -> u64 {
+    let mut hash = iterations;
+    for i in 0..iterations {
+        hash = hash.wrapping_mul(6364136223846793005).wrapping_add(i);
+    }
+    hash
+}
+
+#[scotch_guest::guest_function]
+fn allocate(pages: u32) -> u32 {
+    let memory = std::hint::black_box(vec![0u8; pages as usize * 65536]);
+    (memory.len() / 65536) as u32
+}
+
+#[scotch_guest::guest_function]
+fn spin() {
+    loop {
+        std::hint::black_box(());
+    }
+}
+
+#[scotch_guest::guest_function]
+fn spin_host() {
+    while std::hint::black_box(host_not(false)) {}
+}
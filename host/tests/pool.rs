@@ -0,0 +1,64 @@
+mod common;
+
+use scotch_host::{guest_functions, make_exports, PluginPool, RuntimeError, ScotchHostError};
+use std::{sync::Arc, thread};
+
+#[guest_functions]
+extern "C" {
+    pub fn echo_u32(value: u32) -> u32;
+    pub fn burn(iterations: u64) -> u64;
+}
+
+fn host_error(error: RuntimeError) -> ScotchHostError {
+    error
+        .downcast::<ScotchHostError>()
+        .expect("Call failed with a trap instead of a host error")
+}
+
+#[test]
+fn pool_instances_are_shared_between_threads() {
+    let pool = Arc::new(
+        PluginPool::new(2, common::plugin_bytes(), |builder| {
+            common::with_imports(builder.with_state(())).with_exports(make_exports![echo_u32])
+        })
+        .unwrap(),
+    );
+    assert_eq!(pool.size(), 2);
+
+    let first = pool.get();
+    let second = pool.try_get().unwrap();
+    assert!(pool.try_get().is_none());
+    drop(second);
+    assert_eq!(pool.available(), 1);
+
+    let handles = (0..4)
+        .map(|i| {
+            let pool = pool.clone();
+            thread::spawn(move || pool.get().function_unwrap::<echo_u32>()(i).unwrap())
+        })
+        .collect::<Vec<_>>();
+    drop(first);
+
+    let out = handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(out, [0, 1, 2, 3]);
+    assert_eq!(pool.available(), 2);
+}
+
+#[test]
+fn pool_instances_keep_fuel_settings() {
+    let pool = PluginPool::new(2, common::plugin_bytes(), |builder| {
+        common::with_imports(builder.with_fuel_per_call(100_000).with_state(()))
+            .with_exports(make_exports![burn])
+    })
+    .unwrap();
+
+    let plugins = [pool.get(), pool.get()];
+    for plugin in &plugins {
+        assert!(plugin.fuel().is_some());
+        let error = plugin.function_unwrap::<burn>()(1_000_000).unwrap_err();
+        assert!(matches!(host_error(error), ScotchHostError::FuelExhausted));
+    }
+}
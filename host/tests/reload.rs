@@ -0,0 +1,97 @@
+mod common;
+
+use scotch_host::{guest_functions, make_exports, PollingHotPlugin, WasmPlugin};
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+#[guest_functions]
+extern "C" {
+    pub fn echo_u32(value: u32) -> u32;
+}
+
+fn plugin_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("scotch-{}-{name}.wasm", std::process::id()));
+    fs::write(&path, common::plugin_bytes()).unwrap();
+    path
+}
+
+#[test]
+fn failed_reload_keeps_old_plugin_and_state() {
+    let path = plugin_path("reload");
+    let imports = Arc::new(AtomicBool::new(true));
+    let migrations = Arc::new(AtomicUsize::new(0));
+
+    let mut plugin = PollingHotPlugin::new_with_migration(
+        &path,
+        (),
+        {
+            let imports = imports.clone();
+            move |state| {
+                let builder = WasmPlugin::builder().with_state(state);
+                let builder = if imports.load(Ordering::Relaxed) {
+                    common::with_imports(builder)
+                } else {
+                    builder
+                };
+                builder.with_exports(make_exports![echo_u32])
+            }
+        },
+        {
+            let migrations = migrations.clone();
+            move |_| {
+                migrations.fetch_add(1, Ordering::Relaxed);
+            }
+        },
+    )
+    .unwrap();
+
+    // Module does not compile.
+    fs::write(&path, b"not wasm").unwrap();
+    assert!(plugin.reload().is_err());
+    assert_eq!(migrations.load(Ordering::Relaxed), 0);
+    assert!(plugin.state::<()>().is_some());
+    assert_eq!(plugin.function_unwrap::<echo_u32>()(7).unwrap(), 7);
+
+    // Module compiles but can not be instantiated without imports.
+    fs::write(&path, common::plugin_bytes()).unwrap();
+    imports.store(false, Ordering::Relaxed);
+    assert!(plugin.reload().is_err());
+    assert_eq!(migrations.load(Ordering::Relaxed), 0);
+    assert!(plugin.state::<()>().is_some());
+    assert_eq!(plugin.function_unwrap::<echo_u32>()(7).unwrap(), 7);
+
+    imports.store(true, Ordering::Relaxed);
+    plugin.reload().unwrap();
+    assert_eq!(migrations.load(Ordering::Relaxed), 1);
+    assert_eq!(plugin.function_unwrap::<echo_u32>()(7).unwrap(), 7);
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn changed_file_is_reloaded_when_polled() {
+    let path = plugin_path("poll");
+    let mut plugin = PollingHotPlugin::new(&path, (), |state| {
+        common::with_imports(WasmPlugin::builder().with_state(state))
+            .with_exports(make_exports![echo_u32])
+    })
+    .unwrap();
+    assert!(!plugin.reload_if_changed().unwrap());
+
+    let file = fs::File::options().write(true).open(&path).unwrap();
+    file.set_modified(SystemTime::now() + Duration::from_secs(10))
+        .unwrap();
+    assert!(plugin.reload_if_changed().unwrap());
+    assert!(!plugin.reload_if_changed().unwrap());
+    assert!(plugin.state::<()>().is_some());
+    assert_eq!(plugin.function_unwrap::<echo_u32>()(7).unwrap(), 7);
+
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,116 @@
+#![cfg(feature = "signing")]
+
+mod common;
+
+use ed25519_dalek::SigningKey;
+use scotch_host::{
+    guest_functions, make_exports, sign_plugin, PluginPool, PluginTemplate, ScotchHostError,
+    SignatureError, TrustStore, WasmPlugin,
+};
+
+#[guest_functions]
+extern "C" {
+    pub fn echo_u32(value: u32) -> u32;
+}
+
+fn vendor_key() -> SigningKey {
+    SigningKey::from_bytes(&[7; 32])
+}
+
+fn trust() -> TrustStore {
+    TrustStore::new().trust("acme", vendor_key().verifying_key())
+}
+
+fn signed() -> Vec<u8> {
+    sign_plugin(common::plugin_bytes(), &vendor_key()).unwrap()
+}
+
+/// Changes the last byte covered by the signature, the module structure stays intact.
+fn tampered() -> Vec<u8> {
+    let mut signed = signed();
+    signed[common::plugin_bytes().len() - 1] ^= 1;
+    signed
+}
+
+fn load(bytecode: &[u8]) -> Result<WasmPlugin, ScotchHostError> {
+    let builder = WasmPlugin::builder()
+        .with_trust_store(trust())
+        .with_state(())
+        .from_binary(bytecode)?;
+    common::with_imports(builder)
+        .with_exports(make_exports![echo_u32])
+        .finish()
+}
+
+fn rejection<T>(result: Result<T, ScotchHostError>) -> SignatureError {
+    match result.err().unwrap() {
+        ScotchHostError::SignatureRejected(e) => e,
+        e => panic!("Expected signature error, got {e}"),
+    }
+}
+
+#[test]
+fn signed_plugin_loads() {
+    let plugin = load(&signed()).unwrap();
+    assert_eq!(plugin.function_unwrap::<echo_u32>()(7).unwrap(), 7);
+    assert_eq!(trust().verify(&signed()).unwrap(), "acme");
+}
+
+#[test]
+fn rejects_modified_unsigned_and_untrusted_plugins() {
+    assert_eq!(
+        rejection(load(&tampered())),
+        SignatureError::InvalidSignature
+    );
+    assert_eq!(
+        rejection(load(common::plugin_bytes())),
+        SignatureError::Unsigned
+    );
+
+    let other = SigningKey::from_bytes(&[9; 32]);
+    let foreign = sign_plugin(common::plugin_bytes(), &other).unwrap();
+    assert_eq!(
+        rejection(load(&foreign)),
+        SignatureError::UntrustedKey(other.verifying_key().to_bytes())
+    );
+}
+
+#[test]
+fn pools_and_templates_check_signature() {
+    let pool = PluginPool::new_signed(2, &signed(), &trust(), |builder| {
+        common::with_imports(builder.with_state(()))
+    })
+    .unwrap();
+    assert_eq!(pool.size(), 2);
+
+    let result = PluginPool::new_signed(2, &tampered(), &trust(), |builder| {
+        common::with_imports(builder.with_state(()))
+    });
+    assert_eq!(rejection(result), SignatureError::InvalidSignature);
+
+    let template = PluginTemplate::new_signed(&signed(), &trust(), |builder, state| {
+        common::with_imports(builder.with_state(state))
+    })
+    .unwrap();
+    template.instantiate(()).unwrap();
+
+    let result = PluginTemplate::new_signed(common::plugin_bytes(), &trust(), |builder, state| {
+        common::with_imports(builder.with_state(state))
+    });
+    assert_eq!(rejection(result), SignatureError::Unsigned);
+}
+
+#[test]
+fn rejects_precompiled_module_with_trust_store() {
+    let template = PluginTemplate::new(&tampered(), |builder, state| {
+        common::with_imports(builder.with_state(state))
+    })
+    .unwrap();
+
+    let builder = WasmPlugin::builder()
+        .with_trust_store(trust())
+        .with_state(())
+        .from_module(template.module().clone());
+    let result = common::with_imports(builder).finish();
+    assert_eq!(rejection(result), SignatureError::Precompiled);
+}
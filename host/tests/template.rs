@@ -0,0 +1,81 @@
+mod common;
+
+use scotch_host::{
+    guest_functions, make_exports, PluginTemplate, RuntimeError, ScotchHostError, WasmPlugin,
+};
+use std::time::Duration;
+
+#[guest_functions]
+extern "C" {
+    pub fn echo_u32(value: u32) -> u32;
+    pub fn burn(iterations: u64) -> u64;
+    pub fn spin();
+}
+
+fn host_error(error: RuntimeError) -> ScotchHostError {
+    error
+        .downcast::<ScotchHostError>()
+        .expect("Call failed with a trap instead of a host error")
+}
+
+#[test]
+fn template_instances_have_own_store() {
+    let builder = WasmPlugin::builder()
+        .with_fuel(1_000_000)
+        .from_binary(common::plugin_bytes())
+        .unwrap();
+    let template = PluginTemplate::from_builder(builder, |builder, state| {
+        common::with_imports(builder.with_state(state)).with_exports(make_exports![burn])
+    });
+
+    let first = template.instantiate(()).unwrap();
+    let second = template.instantiate(()).unwrap();
+    let start = second.fuel().unwrap();
+    first.function_unwrap::<burn>()(1_000).unwrap();
+    assert!(first.fuel().unwrap() < start);
+    assert_eq!(second.fuel(), Some(start));
+
+    let pool = template.pool(3, || ()).unwrap();
+    assert_eq!(pool.size(), 3);
+    assert_eq!(pool.get().fuel(), Some(start));
+}
+
+#[test]
+fn template_instances_keep_builder_settings() {
+    let builder = WasmPlugin::builder()
+        .with_fuel_per_call(100_000)
+        .from_binary(common::plugin_bytes())
+        .unwrap();
+    let template = PluginTemplate::from_builder(builder, |builder, state| {
+        common::with_imports(builder.with_state(state)).with_exports(make_exports![burn])
+    });
+
+    let plugin = template.instantiate(()).unwrap();
+    assert!(plugin.fuel().is_some());
+    let error = plugin.function_unwrap::<burn>()(1_000_000).unwrap_err();
+    assert!(matches!(host_error(error), ScotchHostError::FuelExhausted));
+
+    let builder = WasmPlugin::builder()
+        .with_timeout(Duration::from_millis(100))
+        .from_binary(common::plugin_bytes())
+        .unwrap();
+    let template = PluginTemplate::from_builder(builder, |builder, state| {
+        common::with_imports(builder.with_state(state)).with_exports(make_exports![spin])
+    });
+
+    let plugin = template.instantiate(()).unwrap();
+    let error = plugin.function_unwrap::<spin>()().unwrap_err();
+    assert!(matches!(host_error(error), ScotchHostError::Timeout));
+}
+
+#[test]
+fn template_configure_runs_before_instantiation() {
+    let template = PluginTemplate::new(common::plugin_bytes(), |builder, state| {
+        common::with_imports(builder.with_memory_limit(64).with_state(state))
+            .with_exports(make_exports![echo_u32])
+    })
+    .unwrap();
+
+    let plugin = template.instantiate(()).unwrap();
+    assert_eq!(plugin.function_unwrap::<echo_u32>()(3).unwrap(), 3);
+}
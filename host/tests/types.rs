@@ -0,0 +1,232 @@
+mod common;
+
+use scotch_host::{guest_functions, make_exports, GuestError, GuestResultExt, WasmPlugin};
+
+#[guest_functions]
+extern "C" {
+    pub fn echo_bool(value: bool) -> bool;
+    pub fn echo_char(value: char) -> char;
+    pub fn echo_u8(value: u8) -> u8;
+    pub fn echo_u16(value: u16) -> u16;
+    pub fn echo_u32(value: u32) -> u32;
+    pub fn echo_u64(value: u64) -> u64;
+    pub fn echo_i8(value: i8) -> i8;
+    pub fn echo_i16(value: i16) -> i16;
+    pub fn echo_i32(value: i32) -> i32;
+    pub fn echo_i64(value: i64) -> i64;
+    pub fn echo_f32(value: f32) -> f32;
+    pub fn echo_f64(value: f64) -> f64;
+    pub fn echo_usize(value: usize) -> usize;
+    pub fn echo_isize(value: isize) -> isize;
+    pub fn echo_u128(value: u128) -> u128;
+    pub fn echo_i128(value: i128) -> i128;
+    pub fn echo_string(value: &String) -> String;
+    pub fn echo_vec(value: &Vec<i32>) -> Vec<i32>;
+    pub fn echo_tuple(value: &(u8, String)) -> (u8, String);
+    pub fn echo_array(value: &[u32; 3]) -> [u32; 3];
+    pub fn echo_option(value: &Option<i64>) -> Option<i64>;
+    pub fn echo_bytes(value: &[u8]) -> Vec<u8>;
+    pub fn echo_str(value: &str) -> String;
+    pub fn checked_div(a: i32, b: i32) -> Result<i32, String>;
+    pub fn push_range(list: &mut Vec<i32>, count: u32);
+    pub fn call_host(name: &String) -> String;
+}
+
+fn plugin() -> WasmPlugin {
+    common::load(WasmPlugin::builder())
+        .with_exports(make_exports![
+            echo_bool,
+            echo_char,
+            echo_u8,
+            echo_u16,
+            echo_u32,
+            echo_u64,
+            echo_i8,
+            echo_i16,
+            echo_i32,
+            echo_i64,
+            echo_f32,
+            echo_f64,
+            echo_usize,
+            echo_isize,
+            echo_u128,
+            echo_i128,
+            echo_string,
+            echo_vec,
+            echo_tuple,
+            echo_array,
+            echo_option,
+            echo_bytes,
+            echo_str,
+            checked_div,
+            push_range,
+            call_host
+        ])
+        .finish()
+        .unwrap()
+}
+
+#[test]
+fn native_values_round_trip() {
+    let plugin = plugin();
+
+    for value in [true, false] {
+        assert_eq!(plugin.function_unwrap::<echo_bool>()(value).unwrap(), value);
+    }
+    for value in ['a', 'ß', '🦀', char::MAX] {
+        assert_eq!(plugin.function_unwrap::<echo_char>()(value).unwrap(), value);
+    }
+
+    assert_eq!(
+        plugin.function_unwrap::<echo_u8>()(u8::MAX).unwrap(),
+        u8::MAX
+    );
+    assert_eq!(
+        plugin.function_unwrap::<echo_u16>()(u16::MAX).unwrap(),
+        u16::MAX
+    );
+    assert_eq!(
+        plugin.function_unwrap::<echo_u32>()(u32::MAX).unwrap(),
+        u32::MAX
+    );
+    assert_eq!(
+        plugin.function_unwrap::<echo_u64>()(u64::MAX).unwrap(),
+        u64::MAX
+    );
+    assert_eq!(
+        plugin.function_unwrap::<echo_i8>()(i8::MIN).unwrap(),
+        i8::MIN
+    );
+    assert_eq!(
+        plugin.function_unwrap::<echo_i16>()(i16::MIN).unwrap(),
+        i16::MIN
+    );
+    assert_eq!(
+        plugin.function_unwrap::<echo_i32>()(i32::MIN).unwrap(),
+        i32::MIN
+    );
+    assert_eq!(
+        plugin.function_unwrap::<echo_i64>()(i64::MIN).unwrap(),
+        i64::MIN
+    );
+    assert_eq!(plugin.function_unwrap::<echo_f32>()(-1.25).unwrap(), -1.25);
+    assert_eq!(
+        plugin.function_unwrap::<echo_f64>()(f64::MAX).unwrap(),
+        f64::MAX
+    );
+    assert!(plugin.function_unwrap::<echo_f64>()(f64::NAN)
+        .unwrap()
+        .is_nan());
+}
+
+#[test]
+fn pointer_sized_and_wide_integers_round_trip() {
+    let plugin = plugin();
+
+    assert_eq!(
+        plugin.function_unwrap::<echo_usize>()(u32::MAX as usize).unwrap(),
+        u32::MAX as usize
+    );
+    assert_eq!(plugin.function_unwrap::<echo_isize>()(-5).unwrap(), -5);
+    assert!(plugin.function_unwrap::<echo_usize>()(u32::MAX as usize + 1).is_err());
+
+    assert_eq!(
+        plugin.function_unwrap::<echo_u128>()(u128::MAX).unwrap(),
+        u128::MAX
+    );
+    assert_eq!(
+        plugin.function_unwrap::<echo_i128>()(i128::MIN).unwrap(),
+        i128::MIN
+    );
+    assert_eq!(
+        plugin.function_unwrap::<echo_i128>()(u64::MAX as i128 + 1).unwrap(),
+        u64::MAX as i128 + 1
+    );
+}
+
+#[test]
+fn encoded_values_round_trip() {
+    let plugin = plugin();
+
+    let text = "Hello, wasm! ".repeat(10_000);
+    assert_eq!(
+        plugin.function_unwrap::<echo_string>()(&text).unwrap(),
+        text
+    );
+    assert_eq!(
+        plugin.function_unwrap::<echo_string>()(&String::new()).unwrap(),
+        ""
+    );
+
+    let list: Vec<i32> = (-1000..1000).collect();
+    assert_eq!(plugin.function_unwrap::<echo_vec>()(&list).unwrap(), list);
+
+    let tuple = (42, "tuple".to_owned());
+    assert_eq!(
+        plugin.function_unwrap::<echo_tuple>()(&tuple).unwrap(),
+        tuple
+    );
+    assert_eq!(
+        plugin.function_unwrap::<echo_array>()(&[1, 2, 3]).unwrap(),
+        [1, 2, 3]
+    );
+    assert_eq!(
+        plugin.function_unwrap::<echo_option>()(&Some(-7)).unwrap(),
+        Some(-7)
+    );
+    assert_eq!(
+        plugin.function_unwrap::<echo_option>()(&None).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn raw_bytes_round_trip() {
+    let plugin = plugin();
+
+    let bytes: Vec<u8> = (0..=255).cycle().take(100_000).collect();
+    assert_eq!(
+        plugin.function_unwrap::<echo_bytes>()(&bytes).unwrap(),
+        bytes
+    );
+    assert!(plugin.function_unwrap::<echo_bytes>()(&[])
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        plugin.function_unwrap::<echo_str>()("Grüße, 🦀").unwrap(),
+        "Grüße, 🦀"
+    );
+}
+
+#[test]
+fn guest_errors_and_mutable_references() {
+    let plugin = plugin();
+
+    assert_eq!(
+        plugin.function_unwrap::<checked_div>()(7, 2)
+            .flatten_guest()
+            .unwrap(),
+        3
+    );
+    assert!(matches!(
+        plugin.function_unwrap::<checked_div>()(7, 0).flatten_guest(),
+        Err(GuestError::Guest(e)) if e == "Division by zero"
+    ));
+
+    let mut list = vec![-1];
+    plugin.function_unwrap::<push_range>()(&mut list, 3).unwrap();
+    assert_eq!(list, [-1, 0, 1, 2]);
+}
+
+#[test]
+fn host_functions_round_trip() {
+    let plugin = plugin();
+
+    assert_eq!(
+        plugin.function_unwrap::<call_host>()(&"Jack".to_owned()).unwrap(),
+        format!(
+            "true b 3 7 {} Hello, Jack! [1, 2] 14 JACK Ok(0) Err(\"Underflow\") 4",
+            u64::MAX as i128 + 1
+        )
+    );
+}
@@ -0,0 +1,128 @@
+#![cfg(feature = "verify")]
+
+mod common;
+
+use ed25519_dalek::SigningKey;
+use scotch_host::{
+    guest_functions, make_exports, ArtifactSigner, ArtifactVerifier, ScotchHostError,
+    VerificationError, WasmPlugin,
+};
+
+#[guest_functions]
+extern "C" {
+    pub fn echo_u32(value: u32) -> u32;
+}
+
+fn serialized(signer: Option<&ArtifactSigner>) -> Vec<u8> {
+    common::load(WasmPlugin::builder())
+        .finish()
+        .unwrap()
+        .serialize_verified(signer)
+        .unwrap()
+}
+
+fn load(data: &[u8], verifier: &ArtifactVerifier) -> Result<WasmPlugin, ScotchHostError> {
+    let builder = WasmPlugin::builder()
+        .with_state(())
+        .from_verified_serialized(data, verifier)?;
+    common::with_imports(builder)
+        .with_exports(make_exports![echo_u32])
+        .finish()
+}
+
+fn rejection(data: &[u8], verifier: &ArtifactVerifier) -> VerificationError {
+    match load(data, verifier).err().unwrap() {
+        ScotchHostError::VerificationFailed(e) => *e,
+        e => panic!("Expected verification error, got {e}"),
+    }
+}
+
+/// Skips magic, format version, versions of scotch and wasmer and the target.
+fn hash_offset(data: &[u8]) -> usize {
+    (0..3).fold(9, |offset, _| {
+        offset + 2 + u16::from_le_bytes([data[offset], data[offset + 1]]) as usize
+    })
+}
+
+#[test]
+fn hmac_authenticated_plugin_is_loaded() {
+    let data = serialized(Some(&ArtifactSigner::Hmac(b"secret".to_vec())));
+    let verifier = ArtifactVerifier::Hmac(b"secret".to_vec());
+
+    let plugin = load(&data, &verifier).unwrap();
+    assert_eq!(plugin.function_unwrap::<echo_u32>()(7).unwrap(), 7);
+
+    assert_eq!(
+        rejection(&data, &ArtifactVerifier::Hmac(b"other".to_vec())),
+        VerificationError::SignatureMismatch
+    );
+}
+
+#[test]
+fn tampered_plugin_is_rejected() {
+    let data = serialized(Some(&ArtifactSigner::Hmac(b"secret".to_vec())));
+    let verifier = ArtifactVerifier::Hmac(b"secret".to_vec());
+
+    let mut code = data.clone();
+    *code.last_mut().unwrap() ^= 1;
+    assert_eq!(rejection(&code, &verifier), VerificationError::HashMismatch);
+
+    // Flips a byte of the hash in the header, which is covered by the HMAC.
+    let mut header = data.clone();
+    header[hash_offset(&data)] ^= 1;
+    assert_eq!(
+        rejection(&header, &verifier),
+        VerificationError::SignatureMismatch
+    );
+
+    assert_eq!(
+        rejection(&data[..20], &verifier),
+        VerificationError::InvalidHeader
+    );
+    assert_eq!(
+        rejection(b"not a plugin", &verifier),
+        VerificationError::InvalidHeader
+    );
+}
+
+#[test]
+fn ed25519_signed_plugin_is_loaded() {
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let data = serialized(Some(&ArtifactSigner::Ed25519(key.clone())));
+
+    load(&data, &ArtifactVerifier::Ed25519(key.verifying_key())).unwrap();
+
+    let other = SigningKey::from_bytes(&[8; 32]);
+    assert_eq!(
+        rejection(&data, &ArtifactVerifier::Ed25519(other.verifying_key())),
+        VerificationError::SignatureMismatch
+    );
+    assert_eq!(
+        rejection(&data, &ArtifactVerifier::Hmac(b"secret".to_vec())),
+        VerificationError::Unsigned
+    );
+}
+
+#[test]
+fn unsigned_plugin_is_only_loaded_unchecked() {
+    let data = serialized(None);
+    assert_eq!(
+        rejection(&data, &ArtifactVerifier::Hmac(b"secret".to_vec())),
+        VerificationError::Unsigned
+    );
+
+    let builder = WasmPlugin::builder().with_state(());
+    // SAFETY: Plugin was serialized by this test.
+    let builder = unsafe { builder.from_checked_serialized(&data).unwrap() };
+    common::with_imports(builder).finish().unwrap();
+
+    let mut code = data.clone();
+    *code.last_mut().unwrap() ^= 1;
+    let builder = WasmPlugin::builder().with_state(());
+    assert!(matches!(
+        unsafe { builder.from_checked_serialized(&code) }
+            .err()
+            .unwrap(),
+        ScotchHostError::VerificationFailed(e) if *e == VerificationError::HashMismatch
+    ));
+}
@@ -0,0 +1,58 @@
+//! WASI plugin used by the integration tests of `scotch-host`.
+//! It is built for `wasm32-unknown-unknown` and imports the WASI functions it needs directly.
+
+scotch_guest::export_alloc!();
+
+#[repr(C)]
+struct Ciovec {
+    buf: *const u8,
+    len: usize,
+}
+
+#[link(wasm_import_module = "wasi_snapshot_preview1")]
+extern "C" {
+    fn args_sizes_get(count: *mut usize, buf_size: *mut usize) -> u16;
+    fn args_get(ptrs: *mut *mut u8, buf: *mut u8) -> u16;
+    fn environ_sizes_get(count: *mut usize, buf_size: *mut usize) -> u16;
+    fn environ_get(ptrs: *mut *mut u8, buf: *mut u8) -> u16;
+    fn fd_write(fd: u32, iovs: *const Ciovec, iovs_len: usize, written: *mut usize) -> u16;
+}
+
+type SizesGet = unsafe extern "C" fn(*mut usize, *mut usize) -> u16;
+type ListGet = unsafe extern "C" fn(*mut *mut u8, *mut u8) -> u16;
+
+/// Reads NUL-terminated strings of arguments or environment variables.
+fn read_list(sizes_get: SizesGet, get: ListGet) -> Vec<String> {
+    let (mut count, mut size) = (0, 0);
+    assert_eq!(unsafe { sizes_get(&mut count, &mut size) }, 0);
+
+    let mut ptrs = vec![std::ptr::null_mut(); count];
+    let mut buf = vec![0; size];
+    assert_eq!(unsafe { get(ptrs.as_mut_ptr(), buf.as_mut_ptr()) }, 0);
+
+    buf.split(|&b| b == 0)
+        .take(count)
+        .map(|s| String::from_utf8_lossy(s).into_owned())
+        .collect()
+}
+
+fn write_stdout(mut text: &[u8]) {
+    while !text.is_empty() {
+        let iov = Ciovec {
+            buf: text.as_ptr(),
+            len: text.len(),
+        };
+        let mut written = 0;
+        assert_eq!(unsafe { fd_write(1, &iov, 1, &mut written) }, 0);
+        text = &text[written..];
+    }
+}
+
+#[scotch_guest::guest_function]
+fn print_environment() {
+    let args = read_list(args_sizes_get, args_get);
+    let vars = read_list(environ_sizes_get, environ_get);
+
+    write_stdout(format!("args: {}\n", args.join(" ")).as_bytes());
+    write_stdout(format!("env: {}\n", vars.join(" ")).as_bytes());
+}
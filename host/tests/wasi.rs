@@ -0,0 +1,39 @@
+#![cfg(feature = "wasi")]
+
+mod common;
+
+use scotch_host::{guest_functions, make_exports, WasiConfig, WasmPlugin};
+
+#[guest_functions]
+extern "C" {
+    pub fn print_environment();
+}
+
+#[test]
+fn guest_sees_args_and_env_and_stdout_is_captured() {
+    let plugin = WasmPlugin::builder()
+        .with_state(())
+        .with_wasi(
+            WasiConfig::new()
+                .args(["--verbose", "input.txt"])
+                .env("LOG", "debug")
+                .capture_stdout(),
+        )
+        .from_binary(common::wasi_plugin_bytes())
+        .unwrap()
+        .with_exports(make_exports![print_environment])
+        .finish()
+        .unwrap();
+
+    plugin.function_unwrap::<print_environment>()().unwrap();
+    let stdout = String::from_utf8(plugin.take_stdout().unwrap()).unwrap();
+    let mut lines = stdout.lines();
+
+    let args = lines.next().unwrap().strip_prefix("args: ").unwrap();
+    assert!(args.ends_with(" --verbose input.txt"), "{args}");
+    assert_eq!(lines.next(), Some("env: LOG=debug"));
+    assert_eq!(lines.next(), None);
+
+    assert_eq!(plugin.take_stdout(), Some(vec![]));
+    assert_eq!(plugin.take_stderr(), None);
+}